@@ -0,0 +1,762 @@
+// Core graph types with breadth-first and Dijkstra searches, shared by the
+// command-line tool in main.rs and usable on graphs built in memory.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use rayon::prelude::*;
+
+// A graph on vertices 0..n. Each adjacency list is sorted and holds a neighbor
+// once; a self-loop appears as the vertex in its own list. Graphs are undirected
+// unless built with `with_directed_edges`, in which case adjacency[u] lists the
+// heads of u's out-edges. The searches follow adjacency lists, so they respect
+// edge directions; edges_iter, num_edges and connected_components assume the
+// undirected form.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Graph {
+    pub n: usize,
+    pub adjacency: Vec<Vec<usize>>,
+}
+
+impl Graph {
+    // Builds a graph from an edge list, with one vertex per ID up to the largest
+    // endpoint. Repeated edges are stored once.
+    pub fn from_edges(edges: &[(usize, usize)]) -> Graph {
+        let n = edges.iter().map(|&(u, v)| u.max(v) + 1).max().unwrap_or(0);
+        Graph::with_vertices(edges, n)
+    }
+
+    // Builds an undirected graph with `n` vertices from an edge list.
+    // Edges referring to vertices >= n are ignored. Adjacency lists are sorted
+    // and hold each neighbor once, even if the edge appears several times.
+    pub fn with_vertices(edges: &[(usize, usize)], n: usize) -> Graph {
+        let mut adjacency = vec![Vec::new(); n];
+        for &(u, v) in edges {
+            if u < n && v < n {
+                adjacency[u].push(v);
+                adjacency[v].push(u);
+            }
+        }
+
+        // Sort adjacency lists for better consistency and drop duplicate edges
+        for neighbors in &mut adjacency {
+            neighbors.sort();
+            neighbors.dedup();
+        }
+
+        Graph { n, adjacency }
+    }
+
+    // Builds a directed graph with `n` vertices: each edge (u,v) only adds v to
+    // u's adjacency list. Edges referring to vertices >= n are ignored.
+    pub fn with_directed_edges(edges: &[(usize, usize)], n: usize) -> Graph {
+        let mut adjacency = vec![Vec::new(); n];
+        for &(u, v) in edges {
+            if u < n && v < n {
+                adjacency[u].push(v);
+            }
+        }
+        for neighbors in &mut adjacency {
+            neighbors.sort();
+            neighbors.dedup();
+        }
+        Graph { n, adjacency }
+    }
+
+    // Number of distinct directed edges u->v with u != v. For an undirected
+    // graph this counts each edge twice, once per direction.
+    pub fn num_arcs(&self) -> usize {
+        self.adjacency.iter().enumerate()
+            .map(|(u, neighbors)| neighbors.iter().filter(|&&v| v != u).count())
+            .sum()
+    }
+
+    // Iterates over each undirected edge once as (u,v) with u < v.
+    // Self-loops are skipped. Relies on adjacency lists being sorted and deduplicated.
+    pub fn edges_iter(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.adjacency.iter().enumerate().flat_map(|(u, neighbors)| {
+            neighbors.iter().copied().filter(move |&v| u < v).map(move |v| (u, v))
+        })
+    }
+
+    // Number of distinct undirected edges, excluding self-loops
+    pub fn num_edges(&self) -> usize {
+        self.edges_iter().count()
+    }
+
+    // True if the edge u-v exists. Binary search over the sorted adjacency list
+    // of `u` gives O(log degree) lookups. Out-of-range vertices return false.
+    pub fn contains_edge(&self, u: usize, v: usize) -> bool {
+        u < self.n && v < self.n && self.adjacency[u].binary_search(&v).is_ok()
+    }
+
+    // Number of neighbors of `v`, not counting a self-loop
+    pub fn degree(&self, v: usize) -> usize {
+        self.adjacency[v].iter().filter(|&&u| u != v).count()
+    }
+
+    // Compressed sparse row form: the neighbors of `v` are
+    // `indices[indptr[v]..indptr[v + 1]]`, in adjacency-list order.
+    pub fn to_csr(&self) -> (Vec<usize>, Vec<usize>) {
+        let mut indptr = Vec::with_capacity(self.n + 1);
+        let mut indices = Vec::with_capacity(self.adjacency.iter().map(Vec::len).sum());
+        indptr.push(0);
+        for neighbors in &self.adjacency {
+            indices.extend_from_slice(neighbors);
+            indptr.push(indices.len());
+        }
+        (indptr, indices)
+    }
+
+    // Performs a BFS starting from `start`, returning the vertices in the order
+    // they were visited. If `start` is invalid, returns an empty vector.
+    pub fn bfs(&self, start: usize) -> Vec<usize> {
+        if start >= self.n {
+            return Vec::new();
+        }
+
+        let mut visited = vec![false; self.n];
+        let mut queue = VecDeque::new();
+        let mut visited_order = Vec::new();
+
+        visited[start] = true;
+        queue.push_back(start);
+
+        while let Some(current) = queue.pop_front() {
+            visited_order.push(current);
+            for &neighbor in &self.adjacency[current] {
+                if !visited[neighbor] {
+                    visited[neighbor] = true;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        visited_order
+    }
+
+    // Splits the graph into connected components by running BFS from each
+    // unvisited vertex in turn. Isolated vertices form singleton components.
+    pub fn connected_components(&self) -> Vec<Vec<usize>> {
+        let mut seen = vec![false; self.n];
+        let mut components = Vec::new();
+        for v in 0..self.n {
+            if !seen[v] {
+                let component = self.bfs(v);
+                for &u in &component {
+                    seen[u] = true;
+                }
+                components.push(component);
+            }
+        }
+        components
+    }
+
+    // Runs one BFS per source, in parallel across sources, and returns the total
+    // distance and the number of ordered pairs (s, t) with t != s reachable from s.
+    pub fn sum_distances_from(&self, sources: &[usize]) -> (u64, u64) {
+        let histogram = self.distance_counts_from(sources);
+        let total = histogram.iter().enumerate().map(|(d, &count)| d as u64 * count).sum();
+        (total, histogram.iter().sum())
+    }
+
+    // Histogram of the distances from each source to every other vertex it
+    // reaches: entry d counts the ordered pairs (s, t), t != s, at distance d.
+    // Sources are searched in parallel; each worker thread reuses its own distance
+    // buffer and resets only the entries a search touched. Invalid sources
+    // contribute nothing.
+    pub fn distance_counts_from(&self, sources: &[usize]) -> Vec<u64> {
+        sources.par_iter()
+            .map_init(
+                || (vec![usize::MAX; self.n], Vec::new()),
+                |(distances, order), &s| self.histogram_from(s, distances, order),
+            )
+            .reduce(Vec::new, merge_histograms)
+    }
+
+    // The eccentricity of each vertex in `component`, in the same order: the
+    // largest finite distance from it to any vertex it reaches (0 if it reaches
+    // none). Runs one BFS per vertex, in parallel, so it costs O(V*E).
+    pub fn eccentricities(&self, component: &[usize]) -> Vec<usize> {
+        component.par_iter()
+            .map_init(
+                || (vec![usize::MAX; self.n], Vec::new()),
+                |(distances, order), &s| self.histogram_from(s, distances, order).len().saturating_sub(1),
+            )
+            .collect()
+    }
+
+    // The diameter of `component`: the largest eccentricity among its vertices,
+    // or 0 for an empty component.
+    pub fn diameter(&self, component: &[usize]) -> usize {
+        self.eccentricities(component).into_iter().max().unwrap_or(0)
+    }
+
+    // BFS from `s` that counts the vertices found at each distance > 0. The
+    // caller's `distances` buffer must be all usize::MAX and `order` empty; both
+    // are left that way again, so one pair of buffers serves many searches.
+    fn histogram_from(&self, s: usize, distances: &mut [usize], order: &mut Vec<usize>) -> Vec<u64> {
+        let mut histogram = Vec::new();
+        if s >= self.n {
+            return histogram;
+        }
+        // `order` doubles as the BFS queue and the list of entries to reset
+        distances[s] = 0;
+        order.push(s);
+        let mut head = 0;
+        while head < order.len() {
+            let current = order[head];
+            head += 1;
+            for &neighbor in &self.adjacency[current] {
+                if distances[neighbor] == usize::MAX {
+                    let d = distances[current] + 1;
+                    distances[neighbor] = d;
+                    if histogram.len() <= d {
+                        histogram.resize(d + 1, 0);
+                    }
+                    histogram[d] += 1;
+                    order.push(neighbor);
+                }
+            }
+        }
+        for &v in order.iter() {
+            distances[v] = usize::MAX;
+        }
+        order.clear();
+        histogram
+    }
+
+    // Computes the shortest path distance between two vertices using BFS.
+    // Returns None if no path is found or either vertex is out of range. In a
+    // directed graph this is the distance along edge directions, so it can be
+    // None from `start` to `end` while the reverse path exists.
+    pub fn shortest_path(&self, start: usize, end: usize) -> Option<usize> {
+        self.shortest_path_explored(start, end).0
+    }
+
+    // Same as `shortest_path`, but also returns how many vertices the BFS discovered.
+    pub fn shortest_path_explored(&self, start: usize, end: usize) -> (Option<usize>, usize) {
+        if start >= self.n || end >= self.n {
+            return (None, 0);
+        }
+        if start == end {
+            return (Some(0), 1);
+        }
+
+        let mut distances = vec![usize::MAX; self.n];
+        let mut visited = vec![false; self.n];
+        let mut queue = VecDeque::new();
+
+        visited[start] = true;
+        distances[start] = 0;
+        queue.push_back(start);
+        let mut explored = 1;
+
+        while let Some(current) = queue.pop_front() {
+            if current == end {
+                return (Some(distances[end]), explored);
+            }
+            for &neighbor in &self.adjacency[current] {
+                if !visited[neighbor] {
+                    visited[neighbor] = true;
+                    distances[neighbor] = distances[current] + 1;
+                    queue.push_back(neighbor);
+                    explored += 1;
+                }
+            }
+        }
+        (None, explored)
+    }
+
+    // Shortest path distance found by searching from both endpoints at once, always
+    // expanding a full level of whichever frontier is smaller. The level in which the
+    // searches first touch yields the distance. Returns (distance, vertices explored
+    // by both searches); the distance is None if no path exists. Only meaningful for
+    // undirected graphs, since the search from `end` treats edges as two-way.
+    pub fn bidirectional_shortest_path(&self, start: usize, end: usize) -> (Option<usize>, usize) {
+        if start >= self.n || end >= self.n {
+            return (None, 0);
+        }
+        if start == end {
+            return (Some(0), 1);
+        }
+
+        let mut distances = [vec![usize::MAX; self.n], vec![usize::MAX; self.n]];
+        distances[0][start] = 0;
+        distances[1][end] = 0;
+        let mut frontiers = [vec![start], vec![end]];
+        let mut explored = 2;
+
+        while !frontiers[0].is_empty() && !frontiers[1].is_empty() {
+            let side = if frontiers[0].len() <= frontiers[1].len() { 0 } else { 1 };
+            let other = 1 - side;
+            let mut best = None;
+            let mut next = Vec::new();
+            for &u in &frontiers[side] {
+                for &w in &self.adjacency[u] {
+                    if distances[other][w] != usize::MAX {
+                        let through = distances[side][u] + 1 + distances[other][w];
+                        best = Some(best.map_or(through, |b: usize| b.min(through)));
+                    }
+                    if distances[side][w] == usize::MAX {
+                        distances[side][w] = distances[side][u] + 1;
+                        next.push(w);
+                        explored += 1;
+                    }
+                }
+            }
+            // Finish the whole level before stopping, since a later vertex in it may meet closer
+            if best.is_some() {
+                return (best, explored);
+            }
+            frontiers[side] = next;
+        }
+        (None, explored)
+    }
+}
+
+// An undirected graph whose adjacency lists store (neighbor, weight) for each
+// edge. Unlike Graph, parallel edges are kept; Dijkstra uses the lightest one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeightedGraph {
+    pub n: usize,
+    pub adjacency: Vec<Vec<(usize, f64)>>,
+}
+
+// Heap entry for Dijkstra, ordered so that BinaryHeap pops the smallest distance
+struct HeapEntry {
+    distance: f64,
+    vertex: usize,
+}
+
+impl PartialEq for HeapEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for HeapEntry {}
+
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for HeapEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.distance.total_cmp(&self.distance).then(other.vertex.cmp(&self.vertex))
+    }
+}
+
+impl WeightedGraph {
+    // Builds an undirected weighted graph with `n` vertices from (u, v, weight)
+    // edges. Edges referring to vertices >= n are ignored.
+    pub fn with_vertices(edges: &[(usize, usize, f64)], n: usize) -> WeightedGraph {
+        let mut adjacency = vec![Vec::new(); n];
+        for &(u, v, w) in edges {
+            if u < n && v < n {
+                adjacency[u].push((v, w));
+                adjacency[v].push((u, w));
+            }
+        }
+        WeightedGraph { n, adjacency }
+    }
+
+    // Computes the smallest total edge weight from `start` to `end` with
+    // Dijkstra's algorithm and a binary heap. Weights must be non-negative.
+    // Returns None if `end` is unreachable or either vertex is out of range.
+    pub fn dijkstra(&self, start: usize, end: usize) -> Option<f64> {
+        if start >= self.n || end >= self.n {
+            return None;
+        }
+
+        let mut distances = vec![f64::INFINITY; self.n];
+        let mut heap = BinaryHeap::new();
+        distances[start] = 0.0;
+        heap.push(HeapEntry { distance: 0.0, vertex: start });
+
+        while let Some(HeapEntry { distance, vertex }) = heap.pop() {
+            if vertex == end {
+                return Some(distance);
+            }
+            if distance > distances[vertex] {
+                continue;
+            }
+            for &(neighbor, weight) in &self.adjacency[vertex] {
+                let candidate = distance + weight;
+                if candidate < distances[neighbor] {
+                    distances[neighbor] = candidate;
+                    heap.push(HeapEntry { distance: candidate, vertex: neighbor });
+                }
+            }
+        }
+        None
+    }
+}
+
+// Counts distances by value: entry d of the result is the number of times d
+// occurs. The usize::MAX "unreachable" sentinel is left out, so callers count
+// unreachable pairs separately.
+pub fn distance_histogram(distances: &[usize]) -> Vec<u64> {
+    let mut histogram = Vec::new();
+    for &d in distances.iter().filter(|&&d| d != usize::MAX) {
+        if histogram.len() <= d {
+            histogram.resize(d + 1, 0);
+        }
+        histogram[d] += 1;
+    }
+    histogram
+}
+
+// Nearest-rank percentile of a distance histogram: the smallest distance d such
+// that at least a fraction `q` (0 < q <= 1) of the counted distances are <= d.
+// None for an empty histogram.
+pub fn histogram_percentile(histogram: &[u64], q: f64) -> Option<usize> {
+    let total: u64 = histogram.iter().sum();
+    if total == 0 {
+        return None;
+    }
+    let rank = ((q * total as f64).ceil() as u64).clamp(1, total);
+    let mut cumulative = 0;
+    histogram.iter().position(|&count| {
+        cumulative += count;
+        cumulative >= rank
+    })
+}
+
+// Adds two histograms entry by entry
+fn merge_histograms(mut a: Vec<u64>, b: Vec<u64>) -> Vec<u64> {
+    if a.len() < b.len() {
+        return merge_histograms(b, a);
+    }
+    for (x, y) in a.iter_mut().zip(b) {
+        *x += y;
+    }
+    a
+}
+
+// Dense renumbering of the vertex IDs found in an edge list. Files often use
+// large or sparse IDs; a Graph indexed by them directly would allocate an
+// adjacency list for every unused ID below the largest one.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VertexMap {
+    // original[v] is the file's ID for dense vertex v
+    original: Vec<usize>,
+    index: HashMap<usize, usize>,
+    // labels[id] names file ID `id`, for inputs whose vertices were strings
+    labels: Option<Vec<String>>,
+}
+
+impl VertexMap {
+    // Numbers the distinct IDs in `edges` 0..k in increasing order, so IDs that are
+    // already 0..k-1 keep their values, and returns the map with the renumbered edges.
+    pub fn remap(edges: &[(usize, usize)]) -> (VertexMap, Vec<(usize, usize)>) {
+        let mut original: Vec<usize> = edges.iter().flat_map(|&(u, v)| [u, v]).collect();
+        original.sort_unstable();
+        original.dedup();
+        let index: HashMap<usize, usize> = original.iter().enumerate().map(|(v, &id)| (id, v)).collect();
+        let dense = edges.iter().map(|&(u, v)| (index[&u], index[&v])).collect();
+        (VertexMap { original, index, labels: None }, dense)
+    }
+
+    // The map for a graph whose vertex IDs are already 0..n-1
+    pub fn identity(n: usize) -> VertexMap {
+        VertexMap { original: (0..n).collect(), index: (0..n).map(|v| (v, v)).collect(), labels: None }
+    }
+
+    // Attaches a name to every file ID, e.g. the strings that were interned as
+    // IDs 0..k. `labels[id]` is the name of ID `id`.
+    pub fn with_labels(self, labels: Vec<String>) -> VertexMap {
+        VertexMap { labels: Some(labels), ..self }
+    }
+
+    // Number of distinct vertices
+    pub fn len(&self) -> usize {
+        self.original.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.original.is_empty()
+    }
+
+    // Dense index of the file ID `id`, or None if no edge mentions it
+    pub fn index_of(&self, id: usize) -> Option<usize> {
+        self.index.get(&id).copied()
+    }
+
+    // File ID of dense vertex `v`
+    pub fn original_id(&self, v: usize) -> usize {
+        self.original[v]
+    }
+
+    // File IDs of both endpoints of a dense pair
+    pub fn original_pair(&self, (a, b): (usize, usize)) -> (usize, usize) {
+        (self.original[a], self.original[b])
+    }
+
+    // How dense vertex `v` is shown to users: its name if the map has labels,
+    // otherwise its file ID
+    pub fn label(&self, v: usize) -> String {
+        let id = self.original[v];
+        match &self.labels {
+            Some(labels) => labels[id].clone(),
+            None => id.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use std::collections::HashSet;
+
+    // Basic test for BFS traversal
+    #[test]
+    fn test_bfs_small_graph() {
+        // Construct a small graph:
+        // 0 -- 1 -- 2
+        // |    |
+        // 3    4
+        let edges = vec![(0,1),(1,2),(0,3),(1,4)];
+        let n = 5;
+        let mut adjacency = vec![Vec::new(); n];
+        for &(u,v) in &edges {
+            adjacency[u].push(v);
+            adjacency[v].push(u);
+        }
+        for a in &mut adjacency {
+            a.sort();
+        }
+        let graph = Graph { n, adjacency };
+
+        let visited_result = graph.bfs(0);
+        assert_eq!(visited_result.len(), 5);
+    }
+
+    // Basic Test for Shortest Path Computation
+    #[test]
+    fn test_shortest_path_small_graph() {
+        let edges = vec![(0,1),(1,2),(0,3),(1,4)];
+        let n = 5;
+        let mut adjacency = vec![Vec::new(); n];
+        for &(u,v) in &edges {
+            adjacency[u].push(v);
+            adjacency[v].push(u);
+        }
+        for a in &mut adjacency {
+            a.sort();
+        }
+        let graph = Graph { n, adjacency };
+
+        // Distance from 0 to 2 is 2 (0->1->2)
+        let dist_0_2 = graph.shortest_path(0, 2);
+        assert_eq!(dist_0_2, Some(2));
+
+        // Distance from 3 to 4 is 3 (3->0->1->4)
+        let dist_3_4 = graph.shortest_path(3, 4);
+        assert_eq!(dist_3_4, Some(3));
+    }
+
+    // Graphs built in memory size themselves from the largest vertex ID
+    #[test]
+    fn test_from_edges() {
+        let graph = Graph::from_edges(&[(0,1),(1,2),(0,3),(1,4),(6,5),(1,0)]);
+        assert_eq!(graph.n, 7);
+        assert_eq!(graph, Graph::with_vertices(&[(0,1),(1,2),(0,3),(1,4),(5,6)], 7));
+        assert_eq!(graph.num_edges(), 5);
+        assert_eq!(graph.bfs(0), vec![0, 1, 3, 2, 4]);
+        assert_eq!(graph.shortest_path(3, 4), Some(3));
+        assert_eq!(graph.shortest_path(0, 5), None);
+        assert_eq!(graph.shortest_path(0, 7), None);
+
+        let empty = Graph::from_edges(&[]);
+        assert_eq!((empty.n, empty.bfs(0)), (0, Vec::new()));
+    }
+
+    // Every vertex lands in exactly one component, isolated ones on their own
+    #[test]
+    fn test_connected_components() {
+        // 0-1-2 and 3-4, with vertex 5 isolated after 4 and vertex 6 only a self-loop
+        let graph = Graph::with_vertices(&[(0,1),(1,2),(4,3),(6,6)], 7);
+        let components = graph.connected_components();
+        assert_eq!(components, vec![vec![0, 1, 2], vec![3, 4], vec![5], vec![6]]);
+        assert_eq!(components.iter().map(Vec::len).sum::<usize>(), graph.n);
+    }
+
+    // The parallel all-sources sum matches adding up shortest_path one pair at a time
+    #[test]
+    fn test_sum_distances_from_matches_serial() {
+        let graph = Graph::from_edges(&[(0,1),(1,2),(0,3),(1,4)]);
+        let sources: Vec<usize> = (0..graph.n).collect();
+        let (mut total, mut pairs) = (0u64, 0u64);
+        for &s in &sources {
+            for t in 0..graph.n {
+                if let Some(d) = graph.shortest_path(s, t).filter(|&d| d > 0) {
+                    total += d as u64;
+                    pairs += 1;
+                }
+            }
+        }
+        assert_eq!(graph.sum_distances_from(&sources), (total, pairs));
+        assert_eq!((total, pairs), (36, 20));
+
+        // Repeated and invalid sources reuse the thread buffers correctly
+        let with_extra = [0, 0, 9, 4];
+        assert_eq!(graph.sum_distances_from(&with_extra).1, 12);
+        assert_eq!(graph.sum_distances_from(&[]), (0, 0));
+    }
+
+    // Directed graphs only follow edges forwards
+    #[test]
+    fn test_directed_edges() {
+        // 0->1->2 and 2->0 close a cycle; 3->2 can be entered but not left towards 3
+        let graph = Graph::with_directed_edges(&[(0,1),(1,2),(2,0),(3,2),(1,2)], 4);
+        assert_eq!(graph.num_arcs(), 4);
+        assert_eq!(graph.shortest_path(0, 2), Some(2));
+        assert_eq!(graph.shortest_path(2, 1), Some(2));
+        assert_eq!(graph.shortest_path(3, 0), Some(2));
+        assert_eq!(graph.shortest_path(0, 3), None);
+        assert_eq!(graph.sum_distances_from(&[3]), (1 + 2 + 3, 3));
+        assert_eq!(Graph::from_edges(&[(0,1),(1,2)]).num_arcs(), 4);
+    }
+
+    // Histograms skip unreachable pairs, and percentiles use the nearest rank
+    #[test]
+    fn test_distance_histogram() {
+        let histogram = distance_histogram(&[1, 2, 2, usize::MAX, 3, 2, 1, 2, 2, 4]);
+        assert_eq!(histogram, vec![0, 2, 5, 1, 1]);
+        assert_eq!(histogram_percentile(&histogram, 0.5), Some(2));
+        assert_eq!(histogram_percentile(&histogram, 0.8), Some(3));
+        assert_eq!(histogram_percentile(&histogram, 0.9), Some(4));
+        assert_eq!(histogram_percentile(&histogram, 1.0), Some(4));
+        assert_eq!(histogram_percentile(&histogram, 0.1), Some(1));
+        assert!(distance_histogram(&[usize::MAX]).is_empty());
+        assert_eq!(histogram_percentile(&[], 0.5), None);
+
+        // The all-sources histogram agrees with one built from every pair's shortest path
+        let graph = Graph::from_edges(&[(0,1),(1,2),(0,3),(1,4),(5,6)]);
+        let all: Vec<usize> = (0..graph.n)
+            .flat_map(|s| (0..graph.n).filter(move |&t| t != s).map(move |t| (s, t)))
+            .map(|(s, t)| graph.shortest_path(s, t).unwrap_or(usize::MAX))
+            .collect();
+        let sources: Vec<usize> = (0..graph.n).collect();
+        assert_eq!(graph.distance_counts_from(&sources), distance_histogram(&all));
+    }
+
+    // Sparse IDs are renumbered so the graph only holds the vertices that occur
+    #[test]
+    fn test_remap_sparse_ids() {
+        let (ids, dense) = VertexMap::remap(&[(0, 1000000)]);
+        let graph = Graph::from_edges(&dense);
+        assert_eq!(graph.n, 2);
+        assert_eq!(graph.shortest_path(0, 1), Some(1));
+        assert_eq!((ids.index_of(1000000), ids.index_of(5)), (Some(1), None));
+        assert_eq!(ids.original_pair((1, 0)), (1000000, 0));
+
+        // Increasing order leaves already-dense IDs unchanged
+        let edges = vec![(2,0),(1,2),(3,1)];
+        let (ids, dense) = VertexMap::remap(&edges);
+        assert_eq!(dense, edges);
+        assert_eq!(ids, VertexMap::identity(4));
+
+        let (ids, dense) = VertexMap::remap(&[(70, 30), (30, 5000), (70, 70)]);
+        assert_eq!(dense, vec![(1, 0), (0, 2), (1, 1)]);
+        assert_eq!((0..ids.len()).map(|v| ids.original_id(v)).collect::<Vec<_>>(), vec![30, 70, 5000]);
+    }
+
+    // Labels name vertices by file ID; without them the file ID is shown
+    #[test]
+    fn test_vertex_labels() {
+        let (ids, _) = VertexMap::remap(&[(2, 0)]);
+        assert_eq!(ids.label(1), "2");
+        let ids = ids.with_labels(vec!["ada".to_string(), "bo".to_string(), "cy".to_string()]);
+        assert_eq!((ids.label(0), ids.label(1)), ("ada".to_string(), "cy".to_string()));
+        assert_eq!(ids.original_id(1), 2);
+    }
+
+    // Each undirected edge is yielded once, even when listed twice or reversed
+    #[test]
+    fn test_edges_iter_unique() {
+        let edges = vec![(0,1),(1,0),(1,2),(2,3),(1,2),(3,3),(0,3)];
+        let graph = Graph::with_vertices(&edges, 4);
+
+        let listed: Vec<(usize, usize)> = graph.edges_iter().collect();
+        let unique: HashSet<(usize, usize)> = listed.iter().copied().collect();
+        assert_eq!(listed.len(), graph.num_edges());
+        assert_eq!(unique.len(), listed.len());
+        assert_eq!(graph.num_edges(), 4);
+        assert!(listed.iter().all(|&(u, v)| u < v));
+    }
+
+    // Bidirectional BFS agrees with plain BFS on every pair of a random graph
+    #[test]
+    fn test_bidirectional_matches_bfs() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(240);
+        let n = 300;
+        let edges: Vec<(usize, usize)> = (0..450).map(|_| (rng.gen_range(0..n), rng.gen_range(0..n))).collect();
+        let graph = Graph::with_vertices(&edges, n);
+
+        for _ in 0..500 {
+            let (a, b) = (rng.gen_range(0..n), rng.gen_range(0..n));
+            let (forward, _) = graph.shortest_path_explored(a, b);
+            let (both, _) = graph.bidirectional_shortest_path(a, b);
+            assert_eq!(forward, both, "pair {}-{}", a, b);
+        }
+        assert_eq!(graph.bidirectional_shortest_path(0, n), (None, 0));
+    }
+
+    // Slicing the CSR arrays gives back every adjacency list
+    #[test]
+    fn test_to_csr_round_trip() {
+        let edges = vec![(0,1),(1,2),(0,3),(1,4),(2,2),(5,6)];
+        let graph = Graph::with_vertices(&edges, 8);
+        let (indptr, indices) = graph.to_csr();
+        assert_eq!(indptr.len(), graph.n + 1);
+        assert_eq!(*indptr.last().unwrap(), indices.len());
+        for v in 0..graph.n {
+            assert_eq!(&indices[indptr[v]..indptr[v + 1]], graph.adjacency[v].as_slice());
+        }
+    }
+
+    // Eccentricities on a path peak at its ends; the diameter is the path length
+    #[test]
+    fn test_eccentricities_and_diameter() {
+        let graph = Graph::with_vertices(&[(0,1),(1,2),(2,3),(2,4),(5,6)], 8);
+        let component = graph.bfs(0);
+        assert_eq!(component, vec![0, 1, 2, 3, 4]);
+        assert_eq!(graph.eccentricities(&component), vec![3, 2, 2, 3, 3]);
+        assert_eq!(graph.diameter(&component), 3);
+        assert_eq!(graph.eccentricities(&[5, 7]), vec![1, 0]);
+        assert_eq!(graph.diameter(&[]), 0);
+    }
+
+    // Dijkstra takes the lighter three-hop route over the heavy direct edge,
+    // where BFS only counts hops
+    #[test]
+    fn test_dijkstra_prefers_lighter_path() {
+        let weighted = WeightedGraph::with_vertices(&[(0,3,10.0),(0,1,1.0),(1,2,1.5),(2,3,2.0),(3,4,0.5)], 6);
+        assert_eq!(weighted.dijkstra(0, 3), Some(4.5));
+        assert_eq!(weighted.dijkstra(0, 4), Some(5.0));
+        assert_eq!(weighted.dijkstra(0, 5), None);
+        assert_eq!(weighted.dijkstra(0, 6), None);
+
+        let unweighted = Graph::with_vertices(&[(0,3),(0,1),(1,2),(2,3),(3,4)], 6);
+        assert_eq!(unweighted.shortest_path(0, 3), Some(1));
+    }
+
+    // With every weight 1.0, Dijkstra distances equal BFS hop counts
+    #[test]
+    fn test_dijkstra_unit_weights_match_bfs() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(260);
+        let n = 200;
+        let edges: Vec<(usize, usize)> = (0..300).map(|_| (rng.gen_range(0..n), rng.gen_range(0..n))).collect();
+        let graph = Graph::with_vertices(&edges, n);
+        let unit: Vec<(usize, usize, f64)> = edges.iter().map(|&(u, v)| (u, v, 1.0)).collect();
+        let weighted = WeightedGraph::with_vertices(&unit, n);
+
+        for _ in 0..300 {
+            let (a, b) = (rng.gen_range(0..n), rng.gen_range(0..n));
+            assert_eq!(weighted.dijkstra(a, b), graph.shortest_path(a, b).map(|d| d as f64), "pair {}-{}", a, b);
+        }
+    }
+}
@@ -1,258 +1,3943 @@
 // DS210 FInal Project
 // By: Marcos Sasson
 // import crates
-use std::io::BufRead;
+use std::io::{BufRead, BufWriter, Write};
 use std::fs::File;
-use std::collections::{VecDeque, HashSet};
-use rand::Rng;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::{HashMap, VecDeque, HashSet};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use log::{debug, error, info, warn};
+use project::{distance_histogram, histogram_percentile, Graph, VertexMap, WeightedGraph};
 
-struct Graph {
-    n: usize,
-    adjacency: Vec<Vec<usize>>,
+// Edge list analyzed by default
+const DEFAULT_EDGE_FILE: &str = "fb-pages-company_edges.txt";
+// Default number of random vertex pairs sampled for the average distance estimate
+const PAIR_SAMPLE_SIZE: usize = 1000;
+// Default number of new pairs between checkpoints
+const CHECKPOINT_INTERVAL: usize = 100;
+// Peripheral landmark selection evaluates this many candidates per landmark
+const PERIPHERAL_CANDIDATES_PER_LANDMARK: usize = 4;
+// Warn when the standard error exceeds this fraction of the average
+const DEFAULT_INSTABILITY_THRESHOLD: f64 = 0.05;
+// Graphs with more vertices than this get closeness estimated from sampled sources
+const CLOSENESS_EXACT_LIMIT: usize = 5000;
+// Number of BFS sources used when closeness is estimated
+const CLOSENESS_SAMPLE_SOURCES: usize = 500;
+
+// --edge-criticality reruns the estimate once per edge, so it refuses larger graphs
+const EDGE_CRITICALITY_MAX_EDGES: usize = 2000;
+// Number of most critical edges listed by --edge-criticality
+const EDGE_CRITICALITY_TOP: usize = 10;
+// Components larger than this get the --diameter estimated by a double sweep
+const DIAMETER_EXACT_LIMIT: usize = 5000;
+// Largest batch of eccentricities computed in parallel between --algo-timeout checks
+const ECCENTRICITY_BATCH_MAX: usize = 256;
+
+// Prints an error that ends the run to stderr and exits with status 1. Unlike
+// error!, it bypasses the log filter, so RUST_LOG cannot hide why the run stopped.
+macro_rules! fatal {
+    ($($arg:tt)*) => {{
+        eprintln!("error: {}", format!($($arg)*));
+        std::process::exit(1)
+    }};
 }
 
-fn main() {
-    // Print Title
-    println!("--------------------------------------------------------");
-    println!("   Average Distance Between Two Vertices in a Graph");
-    println!("--------------------------------------------------------");
-
-    // Step 1: Read the edge list from file
-    let edges = match read_edge_list("fb-pages-company_edges.txt") {
-        Some(e) => e,
-        None => {
-            eprintln!("Error: Could not read a valid edge list from the file.");
-            return;
+// Set in --logline mode so that only the one-line summary reaches stdout
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+// Like println!, but silenced when QUIET is set. Errors still go to stderr.
+macro_rules! report {
+    ($($arg:tt)*) => {
+        if !QUIET.load(Ordering::Relaxed) {
+            println!($($arg)*);
         }
     };
+}
 
-    if edges.is_empty() {
-        eprintln!("Error: The edge list is empty. Cannot proceed.");
-        return;
+// Summary of the O(V+E) structural metrics of a graph
+struct GraphStats {
+    vertices: usize,
+    edges: usize,
+    density: f64,
+    min_degree: usize,
+    max_degree: usize,
+    mean_degree: f64,
+    median_degree: f64,
+    components: usize,
+    largest_component: usize,
+    isolated: usize,
+    leaves: usize,
+    triangles: u64,
+}
+
+// Supported edge file formats
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum EdgeFormat {
+    // "u,v" or "u,v,w" lines (or space/tab separated), with an optional header
+    #[default]
+    Csv,
+    // Matrix Market coordinate format with 1-indexed entries
+    Mtx,
+    // "a,b" lines of string vertex names after a header line
+    Names,
+}
+
+// How string vertex names are cleaned up before interning (--normalize-ids)
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum IdNormalization {
+    // Names are used exactly as written
+    #[default]
+    Exact,
+    // Leading and trailing whitespace is removed
+    Trim,
+    // Whitespace is removed and names are lowercased
+    Lowercase,
+}
+
+// Edges read from a file of string vertex names
+struct NamedEdges {
+    edges: Vec<(usize, usize)>,
+    // The (normalized) name of each vertex index
+    names: Vec<String>,
+    // Number of distinct raw names that collapsed into an existing vertex
+    merged: usize,
+}
+
+// Per-vertex attributes read from a CSV file with a header row
+struct VertexAttributes {
+    // Attribute names, from the header columns after the vertex column
+    columns: Vec<String>,
+    values: HashMap<usize, Vec<String>>,
+}
+
+impl VertexAttributes {
+    // Returns the value of attribute `key` for vertex `v`, if present
+    fn get(&self, v: usize, key: &str) -> Option<&str> {
+        let column = self.columns.iter().position(|c| c == key)?;
+        self.values.get(&v)?.get(column).map(|s| s.as_str())
     }
+}
 
-    // Determine the number of vertices in the graph
-    let max_vertex_index = edges.iter().flat_map(|&(u,v)| [u,v]).max().unwrap_or(0);
-    let total_vertices = max_vertex_index + 1;
+// Which connected component the distance analyses run on (--component)
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum ComponentChoice {
+    // The component with the most vertices
+    #[default]
+    Largest,
+    // The component containing the BFS start vertex
+    Start,
+}
 
-    // Step 2: Construct an undirected graph
-    let mut adjacency_lists = vec![Vec::new(); total_vertices];
-    for &(u, v) in &edges {
-        if u < total_vertices && v < total_vertices {
-            adjacency_lists[u].push(v);
-            adjacency_lists[v].push(u);
-        }
+// How ALT landmarks are chosen
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum LandmarkStrategy {
+    // Uniformly at random from the component
+    #[default]
+    Random,
+    // The highest-eccentricity vertices among a random candidate sample
+    Peripheral,
+}
+
+// Options parsed from the command line
+#[derive(Debug, Default)]
+struct Config {
+    file: Option<String>,
+    format: EdgeFormat,
+    // Number of pairs to sample; 0 computes the exact average over all pairs
+    samples: usize,
+    // Seed for the random number generator; None draws one from the OS
+    seed: Option<u64>,
+    normalize_ids: IdNormalization,
+    closeness_csv: Option<String>,
+    remove_hubs: Option<usize>,
+    radius_sample: Option<usize>,
+    algo_timeout: Option<f64>,
+    growth_csv: Option<String>,
+    logline: bool,
+    probability_weights: bool,
+    // Also average Dijkstra distances over the sampled pairs, using the third column as weights
+    weighted: bool,
+    dump_graph_stats: bool,
+    alt_landmarks: Option<usize>,
+    exclude_same_level: bool,
+    modularity: Option<String>,
+    dump_pairs: Option<String>,
+    two_core: bool,
+    line_graph: bool,
+    hash: bool,
+    edge_sample: Option<f64>,
+    harmonic_mean: bool,
+    selftest: bool,
+    union: Vec<String>,
+    bootstrap: Option<usize>,
+    attributes: Option<String>,
+    start_where: Option<(String, String)>,
+    six_degrees: bool,
+    six_degrees_hops: usize,
+    parquet: Option<String>,
+    avoid: Option<String>,
+    checkpoint: Option<String>,
+    checkpoint_every: usize,
+    resume: Option<String>,
+    both_directions: bool,
+    // Treat each edge u,v as one-way from u to v
+    directed: bool,
+    landmark_strategy: LandmarkStrategy,
+    component: ComponentChoice,
+    has_edge: Option<(usize, usize)>,
+    single_source_average: Option<usize>,
+    compare_search: Option<(usize, usize)>,
+    instability_threshold: f64,
+    mst: Option<String>,
+    csr: Option<String>,
+    edge_criticality: bool,
+    // Report the diameter of the component, exactly or by double sweep
+    diameter: bool,
+    min_pair_distance: Option<usize>,
+}
+
+// Headline results of a run, used for the --logline summary
+struct RunSummary {
+    file: String,
+    vertices: usize,
+    edges: usize,
+    // None when the run stopped before choosing a start vertex
+    start: Option<String>,
+    visited: usize,
+    pairs: usize,
+    average: Option<f64>,
+    diameter: Option<usize>,
+    seed: Option<u64>,
+}
+
+// Closeness values for every vertex and how they were obtained
+struct Closeness {
+    values: Vec<f64>,
+    // Number of BFS sources that were processed
+    sources: usize,
+    // True when every vertex was used as a source
+    exact: bool,
+    // True when --algo-timeout stopped the computation early
+    timed_out: bool,
+}
+
+// Result of a sampled radius search
+struct RadiusEstimate {
+    radius: usize,
+    vertex: usize,
+    // Number of vertices whose eccentricity was computed
+    sampled: usize,
+    timed_out: bool,
+}
+
+// A pair of vertices found by a double-sweep diameter search
+struct DoubleSweep {
+    diameter: usize,
+    from: usize,
+    to: usize,
+}
+
+// Bootstrap summary of the mean of a sample
+struct Bootstrap {
+    standard_error: f64,
+    // 2.5th and 97.5th percentiles of the resampled means
+    lower: f64,
+    upper: f64,
+}
+
+// BFS distances from each landmark vertex to every vertex
+struct Landmarks {
+    vertices: Vec<usize>,
+    // distances[i][v] is the distance from vertices[i] to v (usize::MAX if unreachable)
+    distances: Vec<Vec<usize>>,
+}
+
+// Averages of the ALT bounds over a set of pairs
+struct AltEstimate {
+    pairs: usize,
+    mean_lower: f64,
+    mean_upper: f64,
+    // Mean of (lower + upper) / 2, the distance estimate
+    mean_midpoint: f64,
+    // Mean of upper - lower, a measure of the estimate's accuracy
+    mean_gap: f64,
+}
+
+// Effect of deleting one edge on the sampled average distance
+struct EdgeImpact {
+    edge: (usize, usize),
+    // Change in the average over the pairs that stay reachable
+    // (infinite when no pair stays reachable)
+    increase: f64,
+    // Sampled pairs that were reachable before the removal but not after
+    disconnected: usize,
+}
+
+// Kinds of output a single distance can be written to
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DistanceFormat {
+    // Human-readable report lines
+    Text,
+    // CSV fields, where unreachable is an empty field
+    Csv,
+}
+
+// Returns None for the usize::MAX "unreachable" sentinel. Typed outputs such as
+// Parquet use this to write a null instead of the sentinel.
+fn finite_distance(d: usize) -> Option<usize> {
+    (d != usize::MAX).then_some(d)
+}
+
+// Renders a distance for the given output, so the sentinel never appears as a number.
+fn format_distance(d: usize, format: DistanceFormat) -> String {
+    match (finite_distance(d), format) {
+        (Some(d), _) => d.to_string(),
+        (None, DistanceFormat::Text) => "unreachable".to_string(),
+        (None, DistanceFormat::Csv) => String::new(),
     }
+}
 
-    // Sort adjacency lists for better consistency
-    for neighbors in &mut adjacency_lists {
-        neighbors.sort();
+// Sampled vertex pairs and the shortest path distance of each one.
+// `distances[i]` belongs to `pairs[i]` and is usize::MAX when unreachable.
+struct DistanceEstimate {
+    pairs: Vec<(usize, usize)>,
+    distances: Vec<usize>,
+}
+
+impl DistanceEstimate {
+    // Number of pairs with a finite distance
+    fn counted_pairs(&self) -> usize {
+        self.distances.iter().filter(|&&d| d != usize::MAX).count()
     }
 
-    let graph = Graph { n: total_vertices, adjacency: adjacency_lists };
+    // Number of pairs with no path between them
+    fn unreachable_pairs(&self) -> usize {
+        self.distances.len() - self.counted_pairs()
+    }
 
-    // Step 3: Perform a BFS from the first vertex found in the edges
-    let start_vertex = edges[0].0;
-    let visited_vertices = bfs_traverse(&graph, start_vertex);
-    println!("\n- BFS started from vertex {} and visited {} vertices.",
-             start_vertex, visited_vertices.len());
+    // Sum of all finite distances
+    fn total_distance(&self) -> usize {
+        self.distances.iter().filter(|&&d| d != usize::MAX).sum()
+    }
 
-    if visited_vertices.len() < 2 {
-        println!("Not enough visited vertices to form pairs (need at least 2).");
-        return;
+    // Average over reachable pairs, or None if no pair is reachable
+    fn average(&self) -> Option<f64> {
+        let counted = self.counted_pairs();
+        if counted == 0 {
+            None
+        } else {
+            Some(self.total_distance() as f64 / counted as f64)
+        }
     }
 
-    // Step 4: Randomly select up to 1000 distinct pairs of reachable vertices
-    // We will choose pairs (a,b) where a<b to avoid duplicates like (b,a).
-    let pair_sample_size = 1000;
-    let mut rng = rand::thread_rng();
-    let visited_count = visited_vertices.len();
-    let mut chosen_pairs = HashSet::new();
-    let mut random_pairs = Vec::new();
+    // Sample standard deviation of the reachable distances, or None if fewer than two
+    fn standard_deviation(&self) -> Option<f64> {
+        let reachable = self.reachable_distances();
+        if reachable.len() < 2 {
+            return None;
+        }
+        let mean = reachable.iter().sum::<usize>() as f64 / reachable.len() as f64;
+        let squares: f64 = reachable.iter().map(|&d| (d as f64 - mean).powi(2)).sum();
+        Some((squares / (reachable.len() - 1) as f64).sqrt())
+    }
 
-    let max_attempts = pair_sample_size * 100;
-    let mut attempts = 0;
+    // Standard error of the average: standard deviation / sqrt(reachable pairs)
+    fn standard_error(&self) -> Option<f64> {
+        self.standard_deviation().map(|sd| sd / (self.counted_pairs() as f64).sqrt())
+    }
 
-    while random_pairs.len() < pair_sample_size && attempts < max_attempts {
-        let i = rng.gen_range(0..visited_count);
-        let j = rng.gen_range(0..visited_count);
+    // Distances of the reachable pairs, in sampling order
+    fn reachable_distances(&self) -> Vec<usize> {
+        self.distances.iter().copied().filter(|&d| d != usize::MAX).collect()
+    }
 
-        if i != j {
-            let a = visited_vertices[i];
-            let b = visited_vertices[j];
-            let ordered_pair = if a < b { (a,b) } else { (b,a) };
+    // Fraction of all sampled pairs at distance <= `hops`. Unreachable pairs
+    // count as farther than any threshold. None if nothing was sampled.
+    fn fraction_within(&self, hops: usize) -> Option<f64> {
+        if self.distances.is_empty() {
+            return None;
+        }
+        let within = self.distances.iter().filter(|&&d| d <= hops).count();
+        Some(within as f64 / self.distances.len() as f64)
+    }
 
-            if !chosen_pairs.contains(&ordered_pair) {
-                chosen_pairs.insert(ordered_pair);
-                random_pairs.push(ordered_pair);
-            }
+    // Shannon entropy, in bits, of the distribution of reachable distances.
+    // 0 when every pair has the same distance. None if no pair is reachable.
+    fn distance_entropy(&self) -> Option<f64> {
+        let reachable = self.reachable_distances();
+        if reachable.is_empty() {
+            return None;
         }
-        attempts += 1;
+        let mut counts: HashMap<usize, usize> = HashMap::new();
+        for &d in &reachable {
+            *counts.entry(d).or_insert(0) += 1;
+        }
+        let total = reachable.len() as f64;
+        let entropy = counts.values()
+            .map(|&c| {
+                let p = c as f64 / total;
+                -p * p.log2()
+            })
+            .sum::<f64>();
+        Some(entropy.max(0.0))
     }
 
-    if random_pairs.is_empty() {
-        println!("Could not form any distinct pairs.");
+    // Harmonic mean over reachable pairs: count / sum of 1/d. Zero distances
+    // (which distinct pairs should never have) are skipped. None if nothing remains.
+    fn harmonic_mean(&self) -> Option<f64> {
+        let positive: Vec<usize> = self.reachable_distances().into_iter().filter(|&d| d > 0).collect();
+        if positive.is_empty() {
+            return None;
+        }
+        let reciprocal_sum: f64 = positive.iter().map(|&d| 1.0 / d as f64).sum();
+        Some(positive.len() as f64 / reciprocal_sum)
+    }
+}
+
+fn main() {
+    // Diagnostics go to stderr through `log`; RUST_LOG overrides the default level
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let config = match parse_args(&args) {
+        Ok(c) => c,
+        Err(msg) => {
+            fatal!("{}", msg)
+        }
+    };
+    QUIET.store(config.logline, Ordering::Relaxed);
+
+    // `selftest` checks every metric on a graph with known answers and exits
+    if config.selftest {
+        let checks = run_selftest();
+        report!("Self-test on the 5-cycle:");
+        for (name, passed) in &checks {
+            report!("  [{}] {}", if *passed { "PASS" } else { "FAIL" }, name);
+        }
+        let failed = checks.iter().filter(|(_, passed)| !passed).count();
+        report!("{} of {} checks passed.", checks.len() - failed, checks.len());
+        if failed > 0 {
+            std::process::exit(1);
+        }
         return;
     }
 
-    // Step 5: Compute shortest path distances for each pair
-    let mut total_distance = 0;
-    let mut counted_pairs = 0;
-    for &(a, b) in &random_pairs {
-        let dist = shortest_path(&graph, a, b);
-        if dist != std::usize::MAX {
-            total_distance += dist;
-            counted_pairs += 1;
+    // Print Title
+    report!("--------------------------------------------------------");
+    report!("   Average Distance Between Two Vertices in a Graph");
+    report!("--------------------------------------------------------");
+
+    // Step 1: Read the edge list from file, or merge several with --union
+    let input_file = config.file.as_deref().unwrap_or(DEFAULT_EDGE_FILE);
+    // With --format names, `names` holds the name of each vertex ID
+    let mut names: Option<Vec<String>> = None;
+    let edges = if config.union.is_empty() {
+        let read = if config.format == EdgeFormat::Names {
+            read_named_edge_list(input_file, config.normalize_ids).map(|named| {
+                report!("\n- Interned {} vertex names.", named.names.len());
+                if config.normalize_ids != IdNormalization::Exact {
+                    report!("- --normalize-ids merged {} names into existing vertices.", named.merged);
+                }
+                names = Some(named.names);
+                named.edges
+            }).ok_or_else(|| format!("{}: no valid edges found", input_file))
+        } else {
+            read_edges(input_file, config.format, config.normalize_ids)
+        };
+        match read {
+            Ok(e) => e,
+            Err(e) => {
+                fatal!("Could not read a valid edge list: {}", e)
+            }
+        }
+    } else {
+        let mut lists = Vec::new();
+        for path in &config.union {
+            let read = if config.format == EdgeFormat::Names {
+                // Every name appears in an edge, so each file's IDs are 0..names.len()
+                // and union_edge_lists offsets them to line up with the concatenated names
+                read_named_edge_list(path, config.normalize_ids).map(|named| {
+                    names.get_or_insert_with(Vec::new).extend(named.names);
+                    named.edges
+                }).ok_or_else(|| format!("{}: no valid edges found", path))
+            } else {
+                read_edges(path, config.format, config.normalize_ids)
+            };
+            match read {
+                Ok(e) => lists.push(e),
+                Err(e) => {
+                    fatal!("Could not read a valid edge list: {}", e)
+                }
+            }
         }
+        let (merged, ranges) = union_edge_lists(&lists);
+        report!("\n- Merged {} graphs with disjoint vertex IDs:", lists.len());
+        for (path, range) in config.union.iter().zip(&ranges) {
+            report!("  {} -> vertices {:?}", path, range);
+        }
+        merged
+    };
+    let input_label = if config.union.is_empty() { input_file.to_string() } else { config.union.join(",") };
+
+    if edges.is_empty() {
+        fatal!("The edge list is empty. Cannot proceed.")
+    }
+
+    // Renumber the vertex IDs densely so that sparse IDs do not inflate the graph.
+    // Vertices are reported, and read from other files, by their original IDs,
+    // and reported by name with --format names.
+    let (ids, edges) = VertexMap::remap(&edges);
+    let ids = match names {
+        Some(names) => ids.with_labels(names),
+        None => ids,
+    };
+
+    // With --seed, every random choice (edge and pair sampling, landmarks, ...) is reproducible
+    let mut rng = seeded_rng(config.seed);
+
+    // With --edge-sample, keep each edge independently with probability p
+    let graph_edges = match config.edge_sample {
+        Some(p) => sample_edges(&edges, p, &mut rng),
+        None => edges.clone(),
+    };
+
+    // Step 2: Construct an undirected graph
+    // With --directed, each edge is one-way and pairs below are ordered: (a,b) is
+    // unreachable when no path follows the edges from a to b, whatever b to a does
+    let graph = if config.directed {
+        let graph = Graph::with_directed_edges(&graph_edges, ids.len());
+        report!("\n- Directed graph has {} vertices and {} edges.", graph.n, graph.num_arcs());
+        graph
+    } else {
+        let graph = Graph::with_vertices(&graph_edges, ids.len());
+        report!("\n- Graph has {} vertices and {} edges.", graph.n, graph.num_edges());
+        graph
+    };
+    if let Some(p) = config.edge_sample {
+        report!("- Edge sampling with p = {}: kept {} of {} input edges.", p, graph_edges.len(), edges.len());
+    }
+
+    if config.hash {
+        report!("- Graph hash: {:016x}", graph_hash(&graph, &ids));
+    }
+    if let Some((a, b)) = config.has_edge {
+        let present = match (ids.index_of(a), ids.index_of(b)) {
+            (Some(u), Some(v)) => graph.contains_edge(u, v),
+            _ => false,
+        };
+        let answer = if present { "present" } else { "absent" };
+        report!("- Edge {}-{}: {}", a, b, answer);
     }
 
-    if counted_pairs == 0 {
-        println!("None of the selected pairs are reachable from each other.");
+    // With --dump-graph-stats, print the cheap structural metrics and stop
+    if config.dump_graph_stats {
+        let stats = compute_graph_stats(&graph);
+        for line in format_graph_stats(&stats) {
+            report!("{}", line);
+        }
+        // No start vertex or pairs were measured, but --logline still gets its line
+        if config.logline {
+            let summary = RunSummary {
+                file: input_label.clone(),
+                vertices: stats.vertices,
+                edges: stats.edges,
+                start: None,
+                visited: 0,
+                pairs: 0,
+                average: None,
+                diameter: None,
+                seed: config.seed,
+            };
+            println!("{}", format_logline(&summary));
+        }
         return;
     }
 
-    // Step 6: Calculate and print the average shortest distance
-    let average_distance = total_distance as f64 / counted_pairs as f64;
-    println!("- Computed distances for {} pairs.", counted_pairs);
-    println!("- Total combined distance: {}", total_distance);
-    println!("- Estimated average shortest path distance: {:.4}", average_distance);
+    // With --two-core, strip tree-like fringes and analyze only the 2-core
+    let graph = if config.two_core {
+        let keep = k_core(&graph, 2);
+        let pruned = keep.iter().filter(|&&k| !k).count();
+        report!("- Restricted to the 2-core: pruned {} vertices, {} remain.", pruned, graph.n - pruned);
+        subgraph(&graph, &keep)
+    } else {
+        graph
+    };
 
-    println!("--------------------------------------------------------");
-    println!("Run Completed.");
-    println!("--------------------------------------------------------");
-}
+    // With --line-graph, analyze distances between edges instead of vertices.
+    // Its vertices are edge numbers, so they are reported as they are.
+    let (graph, ids) = if config.line_graph {
+        let lg = line_graph(&graph);
+        report!("- Line graph has {} vertices and {} edges.", lg.n, lg.num_edges());
+        let lg_ids = VertexMap::identity(lg.n);
+        (lg, lg_ids)
+    } else {
+        (graph, ids)
+    };
 
-// Reads an edge list from a file specified by `path`.
-// Each line should be in the format "u,v" where u and v are integers.
-// Returns Some(vector_of_edges) if successful, or None if no edges found.
-fn read_edge_list(path: &str) -> Option<Vec<(usize, usize)>> {
-    let file = File::open(path).ok()?;
-    let mut lines = std::io::BufReader::new(file).lines();
+    // With --logline, every exit from here on prints exactly one summary line
+    let print_logline = |start: Option<usize>, visited: usize, pairs: usize, average: Option<f64>, diameter: Option<usize>| {
+        if config.logline {
+            let summary = RunSummary {
+                file: input_label.clone(),
+                vertices: graph.n,
+                edges: if config.directed { graph.num_arcs() } else { graph.num_edges() },
+                start: start.map(|v| ids.label(v)),
+                visited,
+                pairs,
+                average,
+                diameter,
+                seed: config.seed,
+            };
+            println!("{}", format_logline(&summary));
+        }
+    };
 
-    // Skip possible header line 
-    lines.next();
+    // Step 3: Perform a BFS from the first vertex found in the edges,
+    // falling back to the first vertex with a neighbor if that one is isolated.
+    // With --start-where, start from the highest-degree vertex matching the attribute.
+    let start_vertex = if let Some((key, value)) = &config.start_where {
+        let path = config.attributes.as_deref().unwrap_or_default();
+        let attributes = match read_attributes(path) {
+            Some(a) => VertexAttributes {
+                values: a.values.into_iter().filter_map(|(id, row)| Some((ids.index_of(id)?, row))).collect(),
+                columns: a.columns,
+            },
+            None => {
+                fatal!("Could not read vertex attributes from {}.", path)
+            }
+        };
+        match choose_start_where(&graph, &attributes, key, value) {
+            Some(v) => {
+                report!("- Starting from vertex {}, the highest-degree vertex with {}={}.",
+                        ids.label(v), key, value);
+                v
+            }
+            None => {
+                fatal!("No vertex in the graph has {}={}.", key, value)
+            }
+        }
+    } else {
+        // On the line graph the first edge's endpoint is not a vertex, so prefer edge 0
+        let preferred = if config.line_graph { 0 } else { edges[0].0 };
+        let start = match choose_start_vertex(&graph, preferred) {
+            Some(v) => v,
+            None => {
+                report!("Every vertex is isolated; there are no pairs to measure.");
+                print_logline(None, 0, 0, None, None);
+                return;
+            }
+        };
+        if start != preferred && !config.line_graph {
+            report!("- Vertex {} from the first edge has no neighbors; starting from vertex {} instead.",
+                    ids.label(preferred), ids.label(start));
+        }
+        start
+    };
+    let visited_vertices = graph.bfs(start_vertex);
+    report!("- BFS started from vertex {} and visited {} vertices.",
+            ids.label(start_vertex), visited_vertices.len());
 
-    let mut edges = Vec::new();
-    for line_result in lines {
-        if let Ok(line_str) = line_result {
-            let parts: Vec<&str> = line_str.trim().split(',').collect();
-            if parts.len() == 2 {
-                if let (Ok(a), Ok(b)) = (parts[0].parse::<usize>(), parts[1].parse::<usize>()) {
-                    edges.push((a, b));
+
+    // Pairs are drawn from the largest component, or with --component start from
+    // the start vertex's component. Directed graphs use weakly connected components.
+    let mut components = if config.directed {
+        Graph::with_vertices(&graph_edges, ids.len()).connected_components()
+    } else {
+        graph.connected_components()
+    };
+    // Scanning in reverse makes ties go to the component found first
+    let largest = (0..components.len()).rev().max_by_key(|&i| components[i].len()).unwrap_or(0);
+    let kind = if config.directed { "weakly connected" } else { "connected" };
+    report!("- Graph has {} {} components; the largest has {} vertices.",
+            components.len(), kind, components[largest].len());
+    let component = match config.component {
+        ComponentChoice::Largest => components.swap_remove(largest),
+        ComponentChoice::Start => visited_vertices.clone(),
+    };
+
+    if component.len() < 2 {
+        report!("Not enough vertices in the component to form pairs (need at least 2).");
+        print_logline(Some(start_vertex), visited_vertices.len(), 0, None, None);
+        return;
+    }
+
+    // Later steps measure from the start vertex, so move it into the chosen component
+    let start_vertex = if component.contains(&start_vertex) {
+        start_vertex
+    } else {
+        let restart = component.iter().copied()
+            .find(|&v| graph.adjacency[v].iter().any(|&u| u != v))
+            .unwrap_or(component[0]);
+        report!("- Vertex {} is outside the largest component; starting from vertex {} instead \
+                 (use --component start to analyze its own).",
+                ids.label(start_vertex), ids.label(restart));
+        restart
+    };
+
+    // With --samples 0, report the exact average over all reachable pairs instead.
+    // There are no sampled pairs then, and parse_args refuses the analyses that need them.
+    let (estimate, average_distance, counted_pairs) = if config.samples == 0 {
+        let histogram = graph.distance_counts_from(&component);
+        let (average, pairs) = match exact_average_distance(&histogram) {
+            Some(result) => result,
+            None => {
+                report!("None of the visited vertices are reachable from each other.");
+                print_logline(Some(start_vertex), visited_vertices.len(), 0, None, None);
+                return;
+            }
+        };
+        report!("- Exact average shortest path distance over all {} ordered pairs: {:.4}", pairs, average);
+        let ordered = component.len() as u64 * (component.len() as u64 - 1);
+        report_distance_distribution(&histogram, ordered - pairs);
+        (DistanceEstimate { pairs: Vec::new(), distances: Vec::new() }, average, pairs as usize)
+    } else {
+        // Step 4: Randomly select up to --samples distinct pairs of reachable vertices
+        // Step 5: Compute shortest path distances for each pair
+        // With --checkpoint or --resume, progress is saved periodically to a file.
+        // With --exclude-same-level, pairs equidistant from the start vertex are skipped.
+        // With --min-pair-distance, only pairs at least that many hops apart are kept.
+        let checkpoint_path = config.checkpoint.as_ref().or(config.resume.as_ref());
+        let estimate = if let Some(path) = checkpoint_path {
+            let resumed = match &config.resume {
+                Some(resume_path) => match read_checkpoint(resume_path, &ids) {
+                    Some(previous) => {
+                        report!("- Resuming from {} with {} pairs already measured.", resume_path, previous.pairs.len());
+                        Some(previous)
+                    }
+                    None => {
+                        fatal!("Could not read a valid checkpoint from {}.", resume_path)
+                    }
+                },
+                None => None,
+            };
+            match estimate_with_checkpoints(&graph, &component, config.samples, &mut rng,
+                                            &Checkpoints { path, every: config.checkpoint_every, ids: &ids },
+                                            resumed) {
+                Ok(e) => e,
+                Err(e) => {
+                    fatal!("Could not write checkpoint {}: {}", path, e)
                 }
             }
+        } else if let Some(min_distance) = config.min_pair_distance {
+            let (estimate, rejected) = sample_far_pairs(&graph, &component, config.samples,
+                                                        min_distance, &mut rng);
+            report!("- Rejected {} candidate pairs closer than {} hops (or unreachable).", rejected, min_distance);
+            estimate
+        } else if config.exclude_same_level {
+            let start_distances = bfs_distances(&graph, start_vertex);
+            let (pairs, excluded) = sample_pairs_where(&component, config.samples, &mut rng,
+                                                       |a, b| start_distances[a] != start_distances[b]);
+            report!("- Excluded {} candidate pairs at the same BFS level from vertex {}.", excluded, ids.label(start_vertex));
+            distances_for_pairs(&graph, pairs)
+        } else if config.directed {
+            distances_for_pairs(&graph, sample_ordered_pairs(&component, config.samples, &mut rng))
+        } else {
+            estimate_average_distance(&graph, &component, config.samples, &mut rng)
+        };
+
+        if estimate.pairs.is_empty() {
+            report!("Could not form any distinct pairs.");
+            print_logline(Some(start_vertex), visited_vertices.len(), 0, None, None);
+            return;
         }
-    }
-    if edges.is_empty() { None } else { Some(edges) }
-}
 
-// Performs a BFS starting from `start_vertex`, returning a vector of visited vertices.
-// If `start_vertex` is invalid, returns an empty vector.
-fn bfs_traverse(graph: &Graph, start_vertex: usize) -> Vec<usize> {
-    if start_vertex >= graph.n {
-        return Vec::new();
+        let average_distance = match estimate.average() {
+            Some(avg) => avg,
+            None => {
+                report!("None of the selected pairs are reachable from each other.");
+                print_logline(Some(start_vertex), visited_vertices.len(), 0, None, None);
+                return;
+            }
+        };
+
+        // Step 6: Print the average shortest distance
+        report!("- Computed distances for {} pairs.", estimate.counted_pairs());
+        report!("- Total combined distance: {}", estimate.total_distance());
+        report!("- Estimated average shortest path distance: {:.4}", average_distance);
+        report_distance_distribution(&distance_histogram(&estimate.distances), estimate.unreachable_pairs() as u64);
+        if config.weighted {
+            report_weighted_average(input_file, config.format, config.normalize_ids, &ids, &estimate.pairs);
+        }
+        if let Some(warning) = instability_warning(&estimate, config.instability_threshold) {
+            warn!("{}", warning);
+        }
+        if let Some(entropy) = estimate.distance_entropy() {
+            report!("- Distance distribution entropy: {:.4} bits", entropy);
+        }
+        if config.six_degrees {
+            if let Some(fraction) = estimate.fraction_within(config.six_degrees_hops) {
+                report!("- Pairs within {} hops: {:.2}%", config.six_degrees_hops, 100.0 * fraction);
+            }
+        }
+        if let Some(resamples) = config.bootstrap {
+            if let Some(boot) = bootstrap_mean(&estimate.reachable_distances(), resamples, &mut rng) {
+                report!("- Bootstrap ({} resamples): standard error {:.4}, 95% interval [{:.4}, {:.4}]",
+                        resamples, boot.standard_error, boot.lower, boot.upper);
+            }
+        }
+        if config.harmonic_mean {
+            if let Some(harmonic) = estimate.harmonic_mean() {
+                report!("- Harmonic mean of sampled distances: {:.4}", harmonic);
+            }
+        }
+        let counted_pairs = estimate.counted_pairs();
+        (estimate, average_distance, counted_pairs)
+    };
+
+    // Step 7: Optionally treat edge weights as probabilities and find most-probable paths
+    if config.probability_weights {
+        report_most_probable_paths(input_file, config.format, config.normalize_ids, &ids, &estimate.pairs);
     }
 
-    let mut visited = vec![false; graph.n];
-    let mut queue = VecDeque::new();
-    let mut visited_order = Vec::new();
+    // Step 8: Optionally write neighborhood growth around the start vertex
+    if let Some(path) = &config.growth_csv {
+        let levels = bfs_levels(&graph, start_vertex);
+        match write_growth_csv(path, &levels) {
+            Ok(()) => report!("- Wrote neighborhood growth for {} radii to {}", levels.len(), path),
+            Err(e) => error!("Could not write growth CSV to {}: {}", path, e),
+        }
+    }
 
-    visited[start_vertex] = true;
-    queue.push_back(start_vertex);
+    // Per-vertex algorithms below share a single time budget
+    let deadline = config.algo_timeout.map_or_else(Deadline::default, Deadline::after_seconds);
 
-    while let Some(current) = queue.pop_front() {
-        visited_order.push(current);
-        for &neighbor in &graph.adjacency[current] {
-            if !visited[neighbor] {
-                visited[neighbor] = true;
-                queue.push_back(neighbor);
+    // Step 9: Optionally write closeness centrality for every vertex
+    if let Some(path) = &config.closeness_csv {
+        let closeness = compute_closeness(&graph, &deadline, &mut rng);
+        match write_closeness_csv(path, &closeness.values, &ids) {
+            Ok(()) => {
+                let method = if closeness.timed_out {
+                    format!("partial: --algo-timeout reached after {} sources", closeness.sources)
+                } else if closeness.exact {
+                    "exact".to_string()
+                } else {
+                    format!("estimated from {} sampled sources", closeness.sources)
+                };
+                report!("- Wrote closeness for {} vertices to {} ({})", closeness.values.len(), path, method);
             }
+            Err(e) => error!("Could not write closeness CSV to {}: {}", path, e),
         }
     }
-    visited_order
-}
 
-// Computes the shortest path distance between two vertices using BFS.
-// Returns std::usize::MAX if no path is found.
-fn shortest_path(graph: &Graph, start: usize, end: usize) -> usize {
-    if start >= graph.n || end >= graph.n {
-        return std::usize::MAX;
-    }
-    if start == end {
-        return 0;
+    // Step 10: Optionally estimate the radius from a sample of eccentricities
+    if let Some(k) = config.radius_sample {
+        if let Some(est) = estimate_radius(&graph, &component, k, &deadline, &mut rng) {
+            if est.timed_out {
+                report!("- Estimated radius (partial: --algo-timeout reached after {} vertices): {} (vertex {})",
+                        est.sampled, est.radius, ids.label(est.vertex));
+            } else if est.sampled == component.len() {
+                report!("- Radius of the component (exact, all {} vertices): {} (center {})",
+                        est.sampled, est.radius, ids.label(est.vertex));
+            } else {
+                report!("- Estimated radius (upper bound from {} sampled vertices): {} (vertex {})",
+                        est.sampled, est.radius, ids.label(est.vertex));
+            }
+        }
     }
 
-    let mut distances = vec![std::usize::MAX; graph.n];
-    let mut visited = vec![false; graph.n];
-    let mut queue = VecDeque::new();
+    // Step 11: Optionally remove the top-k hubs and re-estimate (targeted attack)
+    if let Some(k) = config.remove_hubs {
+        let hubs = top_degree_vertices(&graph, k);
+        let mut keep = vec![true; graph.n];
+        for &h in &hubs {
+            keep[h] = false;
+        }
+        let reduced = subgraph(&graph, &keep);
+        let survivors: Vec<usize> = component.iter().copied().filter(|&v| keep[v]).collect();
+        let after = estimate_average_distance(&reduced, &survivors, config.samples, &mut rng);
 
-    visited[start] = true;
-    distances[start] = 0;
-    queue.push_back(start);
+        let hub_ids: Vec<String> = hubs.iter().map(|&h| ids.label(h)).collect();
+        report!("\n- Removed {} highest-degree vertices: {:?}", hubs.len(), hub_ids);
+        report!("- Average distance before removal: {:.4}", average_distance);
+        match after.average() {
+            Some(avg) => {
+                report!("- Average distance after removal: {:.4}", avg);
+                report!("- Change: {:+.4}", avg - average_distance);
+            }
+            None => report!("- Average distance after removal: no reachable pairs remain"),
+        }
+        report!("- Sampled pairs disconnected by the removal: {} of {}",
+                after.unreachable_pairs(), after.pairs.len());
+    }
 
-    while let Some(current) = queue.pop_front() {
-        if current == end {
-            return distances[end];
+    // Step 12: Optionally approximate the sampled distances with ALT landmark bounds.
+    // Peripheral landmarks are compared against random ones on the same pairs.
+    if let Some(k) = config.alt_landmarks {
+        let mut strategies = vec![LandmarkStrategy::Random];
+        if config.landmark_strategy == LandmarkStrategy::Peripheral {
+            strategies.insert(0, LandmarkStrategy::Peripheral);
         }
-        for &neighbor in &graph.adjacency[current] {
-            if !visited[neighbor] {
-                visited[neighbor] = true;
-                distances[neighbor] = distances[current] + 1;
-                queue.push_back(neighbor);
+        for strategy in strategies {
+            let chosen = match strategy {
+                LandmarkStrategy::Random => select_random_landmarks(&component, k, &mut rng),
+                LandmarkStrategy::Peripheral => select_peripheral_landmarks(&graph, &component, k, &mut rng),
+            };
+            let landmarks = compute_landmarks(&graph, chosen);
+            match alt_estimate(&landmarks, &estimate.pairs) {
+                Some(alt) => {
+                    report!("\n- ALT estimate with {} {:?} landmarks over {} pairs: {:.4} (exact sample average {:.4})",
+                            landmarks.vertices.len(), strategy, alt.pairs, alt.mean_midpoint, average_distance);
+                    report!("- Mean bounds: lower {:.4}, upper {:.4}, average gap {:.4}",
+                            alt.mean_lower, alt.mean_upper, alt.mean_gap);
+                }
+                None => report!("\n- {:?} ALT landmarks could not bound any sampled pair.", strategy),
             }
         }
     }
-    std::usize::MAX
-}
 
-// Basic test for BFS traversal 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    // Step 13: Optionally evaluate a community partition with Newman's modularity
+    if let Some(path) = &config.modularity {
+        match read_communities(path, &ids) {
+            Some(community_of) => {
+                let communities: HashSet<usize> = community_of.iter().copied().collect();
+                report!("- Modularity of the {} communities in {}: {:.6}",
+                        communities.len(), path, modularity(&graph, &community_of));
+            }
+            None => error!("Could not read communities from {}.", path),
+        }
+    }
 
-    #[test]
-    fn test_bfs_small_graph() {
-        // Construct a small graph:
-        // 0 -- 1 -- 2
-        // |    |
-        // 3    4
-        let edges = vec![(0,1),(1,2),(0,3),(1,4)];
-        let n = 5;
-        let mut adjacency = vec![Vec::new(); n];
-        for &(u,v) in &edges {
-            adjacency[u].push(v);
-            adjacency[v].push(u);
+    // Step 14: Optionally dump the sampled pairs so the run can be audited
+    if let Some(path) = &config.dump_pairs {
+        let pairs: Vec<(String, String)> = estimate.pairs.iter().map(|&(a, b)| (ids.label(a), ids.label(b))).collect();
+        match write_pairs_csv(path, &pairs) {
+            Ok(()) => report!("- Wrote {} sampled pairs to {}", estimate.pairs.len(), path),
+            Err(e) => error!("Could not write sampled pairs to {}: {}", path, e),
         }
-        for a in &mut adjacency {
-            a.sort();
+    }
+
+    // Step 15: Optionally re-measure the sampled pairs while avoiding failed vertices
+    if let Some(path) = &config.avoid {
+        match read_vertex_set(path, &ids) {
+            Some(avoid) => report_avoidance(&graph, &estimate, &avoid),
+            None => error!("Could not read vertices to avoid from {}.", path),
         }
-        let graph = Graph { n, adjacency };
+    }
 
-        let visited_result = bfs_traverse(&graph, 0);
-        assert_eq!(visited_result.len(), 5);
+    // Step 16: Optionally compare directed and undirected readings of the same edges.
+    // Each sampled pair is measured in a random direction.
+    if config.both_directions {
+        let oriented: Vec<(usize, usize)> = estimate.pairs.iter()
+            .map(|&(a, b)| if rng.gen_bool(0.5) { (a, b) } else { (b, a) })
+            .collect();
+        let (directed, undirected) = directed_and_undirected_estimates(&graph_edges, ids.len(), &oriented);
+        let format_average = |e: &DistanceEstimate| e.average().map_or("n/a".to_string(), |a| format!("{:.4}", a));
+        report!("\n- Directed vs undirected over {} sampled pairs:", oriented.len());
+        report!("- Directed average: {} ({} pairs unreachable)", format_average(&directed), directed.unreachable_pairs());
+        report!("- Undirected average: {} ({} pairs unreachable)",
+                format_average(&undirected), undirected.unreachable_pairs());
     }
 
-    // Basic Test for Shortest Path Computation
-    #[test]
-    fn test_shortest_path_small_graph() {
-        let edges = vec![(0,1),(1,2),(0,3),(1,4)];
-        let n = 5;
-        let mut adjacency = vec![Vec::new(); n];
-        for &(u,v) in &edges {
-            adjacency[u].push(v);
-            adjacency[v].push(u);
+    // Step 17: Optionally report the exact mean distance from one source
+    if let Some(source) = config.single_source_average {
+        match ids.index_of(source).and_then(|v| single_source_average(&graph, v)) {
+            Some((avg, reached)) => report!("- Average distance from vertex {} to its {} reachable vertices: {:.4}",
+                                            source, reached, avg),
+            None => report!("- Vertex {} reaches no other vertex.", source),
+        }
+    }
+
+    // Step 18: Optionally write a minimum spanning tree (forest) of the weighted input
+    if let Some(path) = &config.mst {
+        match read_weighted_edges(input_file, config.format, config.normalize_ids) {
+            Ok(weighted) => {
+                let dense: Vec<(usize, usize, f64)> = weighted.iter()
+                    .filter_map(|&(u, v, w)| Some((ids.index_of(u)?, ids.index_of(v)?, w)))
+                    .collect();
+                let (forest, total_weight) = minimum_spanning_forest(ids.len(), &dense);
+                let forest: Vec<(String, String, f64)> = forest.into_iter()
+                    .map(|(u, v, w)| (ids.label(u), ids.label(v), w))
+                    .collect();
+                match write_weighted_edges_csv(path, &forest) {
+                    Ok(()) => report!("- Wrote minimum spanning forest with {} edges to {} (total weight {:.4})",
+                                      forest.len(), path, total_weight),
+                    Err(e) => error!("Could not write spanning tree to {}: {}", path, e),
+                }
+            }
+            Err(e) => error!("Could not read a weighted edge list: {}", e),
+        }
+    }
+
+    // Step 19: Optionally write the per-pair distance table as Parquet
+    if let Some(path) = &config.parquet {
+        #[cfg(feature = "parquet")]
+        {
+            let original = DistanceEstimate {
+                pairs: estimate.pairs.iter().map(|&p| ids.original_pair(p)).collect(),
+                distances: estimate.distances.clone(),
+            };
+            match write_pairs_parquet(path, &original) {
+                Ok(()) => report!("- Wrote {} pair distances to {} (Parquet)", estimate.pairs.len(), path),
+                Err(e) => error!("Could not write Parquet file {}: {}", path, e),
+            }
+        }
+        #[cfg(not(feature = "parquet"))]
+        error!("Writing {} requires building with `--features parquet`.", path);
+    }
+
+    // Step 20: Optionally time plain and bidirectional BFS on one pair
+    if let Some((a, b)) = config.compare_search {
+        // IDs missing from the graph map past its last vertex, which both searches reject
+        let (u, v) = (ids.index_of(a).unwrap_or(graph.n), ids.index_of(b).unwrap_or(graph.n));
+        let timer = Instant::now();
+        let (bfs_distance, bfs_explored) = graph.shortest_path_explored(u, v);
+        let bfs_time = timer.elapsed();
+        let timer = Instant::now();
+        let (bidi_distance, bidi_explored) = graph.bidirectional_shortest_path(u, v);
+        let bidi_time = timer.elapsed();
+        assert_eq!(bfs_distance, bidi_distance, "BFS and bidirectional BFS disagree on {}-{}", a, b);
+
+        let shown = format_distance(bfs_distance.unwrap_or(usize::MAX), DistanceFormat::Text);
+        report!("- Distance {}-{}: {}", a, b, shown);
+        report!("  BFS:               {:>10.3?}, {} vertices explored", bfs_time, bfs_explored);
+        report!("  Bidirectional BFS: {:>10.3?}, {} vertices explored", bidi_time, bidi_explored);
+    }
+
+    // Step 21: Optionally export the adjacency structure as CSR arrays
+    if let Some(prefix) = &config.csr {
+        match write_csr(prefix, &graph, &ids) {
+            Ok(()) => report!("- Wrote CSR arrays to {0}_indptr.txt and {0}_indices.txt, \
+                               with the original ID of each row in {0}_ids.txt", prefix),
+            Err(e) => error!("Could not write CSR arrays with prefix {}: {}", prefix, e),
+        }
+    }
+
+    // Step 22: Optionally rank edges by how much their removal hurts the sampled distances
+    if config.edge_criticality {
+        let m = graph.num_edges();
+        if m > EDGE_CRITICALITY_MAX_EDGES {
+            error!("--edge-criticality is limited to graphs with at most {} edges (this one has {}).",
+                   EDGE_CRITICALITY_MAX_EDGES, m);
+        } else {
+            let impacts = edge_criticality(&graph, &estimate.pairs);
+            report!("\n- Most critical of {} edges over {} sampled pairs:", m, estimate.pairs.len());
+            for impact in impacts.iter().take(EDGE_CRITICALITY_TOP) {
+                let (u, v) = (ids.label(impact.edge.0), ids.label(impact.edge.1));
+                report!("  {}-{}: average {:+.4}, {} pairs disconnected",
+                        u, v, impact.increase, impact.disconnected);
+            }
         }
-        for a in &mut adjacency {
-            a.sort();
+    }
+
+    // Step 23: Optionally report the diameter, exactly for components up to
+    // DIAMETER_EXACT_LIMIT vertices and as a double-sweep lower bound beyond that
+    let mut diameter_found = None;
+    if config.diameter {
+        if component.len() <= DIAMETER_EXACT_LIMIT {
+            let eccentricities = eccentricities_until(&graph, &component, &deadline);
+            if let Some((diameter, vertex)) = eccentricities.iter().zip(&component)
+                .map(|(&ecc, &v)| (ecc, v))
+                .max_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1))) {
+                diameter_found = Some(diameter);
+                if eccentricities.len() < component.len() {
+                    report!("- Diameter lower bound (partial: --algo-timeout reached after {} of {} vertices): {} (vertex {})",
+                            eccentricities.len(), component.len(), diameter, ids.label(vertex));
+                } else {
+                    report!("- Diameter of the component (exact, all {} eccentricities): {} (vertex {})",
+                            component.len(), diameter, ids.label(vertex));
+                }
+            }
+        } else if let Some(sweep) = double_sweep_diameter(&graph, &component, &mut rng) {
+            diameter_found = Some(sweep.diameter);
+            report!("- Estimated diameter (double-sweep lower bound; the component has over {} vertices): {} (vertex {} to {})",
+                    DIAMETER_EXACT_LIMIT, sweep.diameter, ids.label(sweep.from), ids.label(sweep.to));
         }
-        let graph = Graph { n, adjacency };
+    }
 
-        // Distance from 0 to 2 is 2 (0->1->2)
-        let dist_0_2 = shortest_path(&graph, 0, 2);
-        assert_eq!(dist_0_2, 2);
+    print_logline(Some(start_vertex), visited_vertices.len(), counted_pairs,
+                  Some(average_distance), diameter_found);
 
-        // Distance from 3 to 4 is 3 (3->0->1->4)
-        let dist_3_4 = shortest_path(&graph, 3, 4);
-        assert_eq!(dist_3_4, 3);
+    report!("--------------------------------------------------------");
+    report!("Run Completed.");
+    report!("--------------------------------------------------------");
+}
+
+// Parses command-line arguments (excluding the program name) into a Config.
+// Returns Err with a message for unknown flags or missing values.
+fn parse_args(args: &[String]) -> Result<Config, String> {
+    let mut config = Config {
+        samples: PAIR_SAMPLE_SIZE,
+        six_degrees_hops: 6,
+        checkpoint_every: CHECKPOINT_INTERVAL,
+        instability_threshold: DEFAULT_INSTABILITY_THRESHOLD,
+        ..Config::default()
+    };
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "selftest" => config.selftest = true,
+            "--file" => {
+                let path = iter.next().ok_or("--file requires a file path")?;
+                config.file = Some(path.clone());
+            }
+            "--samples" => {
+                let value = iter.next().ok_or("--samples requires a pair count")?;
+                config.samples = value.parse::<usize>()
+                    .map_err(|_| format!("Invalid sample count '{}' for --samples (expected 0 or more)", value))?;
+            }
+            "--seed" => {
+                let value = iter.next().ok_or("--seed requires a number")?;
+                let seed = value.parse::<u64>()
+                    .map_err(|_| format!("Invalid seed '{}' for --seed (expected an unsigned integer)", value))?;
+                config.seed = Some(seed);
+            }
+            "--format" => {
+                let value = iter.next().ok_or("--format requires a format name")?;
+                config.format = match value.as_str() {
+                    "csv" => EdgeFormat::Csv,
+                    "mtx" => EdgeFormat::Mtx,
+                    "names" => EdgeFormat::Names,
+                    other => return Err(format!("Unknown format '{}' (expected csv, mtx or names)", other)),
+                };
+            }
+            "--normalize-ids" => {
+                let value = iter.next().ok_or("--normalize-ids requires a mode (trim or lower)")?;
+                config.normalize_ids = match value.as_str() {
+                    "trim" => IdNormalization::Trim,
+                    "lower" => IdNormalization::Lowercase,
+                    other => return Err(format!("Unknown --normalize-ids mode '{}' (expected trim or lower)", other)),
+                };
+            }
+            "--closeness-csv" => {
+                let path = iter.next().ok_or("--closeness-csv requires a file path")?;
+                config.closeness_csv = Some(path.clone());
+            }
+            "--remove-hubs" => {
+                let value = iter.next().ok_or("--remove-hubs requires a vertex count")?;
+                let k = value.parse::<usize>()
+                    .map_err(|_| format!("Invalid hub count '{}' for --remove-hubs", value))?;
+                config.remove_hubs = Some(k);
+            }
+            "--radius-sample" => {
+                let value = iter.next().ok_or("--radius-sample requires a sample size")?;
+                let k = value.parse::<usize>().ok().filter(|&k| k > 0)
+                    .ok_or(format!("Invalid sample size '{}' for --radius-sample", value))?;
+                config.radius_sample = Some(k);
+            }
+            "--algo-timeout" => {
+                let value = iter.next().ok_or("--algo-timeout requires a number of seconds")?;
+                let seconds = value.parse::<f64>().ok().filter(|s| *s >= 0.0 && s.is_finite())
+                    .ok_or(format!("Invalid timeout '{}' for --algo-timeout", value))?;
+                config.algo_timeout = Some(seconds);
+            }
+            "--growth-csv" => {
+                let path = iter.next().ok_or("--growth-csv requires a file path")?;
+                config.growth_csv = Some(path.clone());
+            }
+            "--logline" => config.logline = true,
+            "--probability-weights" => config.probability_weights = true,
+            "--weighted" => config.weighted = true,
+            "--dump-graph-stats" => config.dump_graph_stats = true,
+            "--alt-landmarks" => {
+                let value = iter.next().ok_or("--alt-landmarks requires a landmark count")?;
+                let k = value.parse::<usize>().ok().filter(|&k| k > 0)
+                    .ok_or(format!("Invalid landmark count '{}' for --alt-landmarks", value))?;
+                config.alt_landmarks = Some(k);
+            }
+            "--exclude-same-level" => config.exclude_same_level = true,
+            "--modularity" => {
+                let path = iter.next().ok_or("--modularity requires a community file path")?;
+                config.modularity = Some(path.clone());
+            }
+            "--dump-pairs" => {
+                let path = iter.next().ok_or("--dump-pairs requires a file path")?;
+                config.dump_pairs = Some(path.clone());
+            }
+            "--two-core" => config.two_core = true,
+            "--line-graph" => config.line_graph = true,
+            "--hash" => config.hash = true,
+            "--edge-sample" => {
+                let value = iter.next().ok_or("--edge-sample requires a probability")?;
+                let p = value.parse::<f64>().ok().filter(|p| (0.0..=1.0).contains(p))
+                    .ok_or(format!("Invalid probability '{}' for --edge-sample (expected 0 to 1)", value))?;
+                config.edge_sample = Some(p);
+            }
+            "--harmonic-mean" => config.harmonic_mean = true,
+            "--union" => {
+                while let Some(path) = iter.as_slice().first().filter(|a| !a.starts_with("--")) {
+                    config.union.push(path.clone());
+                    iter.next();
+                }
+                if config.union.is_empty() {
+                    return Err("--union requires at least one file path".to_string());
+                }
+            }
+            "--bootstrap" => {
+                let value = iter.next().ok_or("--bootstrap requires a number of resamples")?;
+                let b = value.parse::<usize>().ok().filter(|&b| b > 1)
+                    .ok_or(format!("Invalid resample count '{}' for --bootstrap (need at least 2)", value))?;
+                config.bootstrap = Some(b);
+            }
+            "--attributes" => {
+                let path = iter.next().ok_or("--attributes requires a file path")?;
+                config.attributes = Some(path.clone());
+            }
+            "--start-where" => {
+                let value = iter.next().ok_or("--start-where requires key=value")?;
+                let (key, wanted) = value.split_once('=')
+                    .ok_or(format!("Invalid filter '{}' for --start-where (expected key=value)", value))?;
+                config.start_where = Some((key.to_string(), wanted.to_string()));
+            }
+            "--six-degrees" => config.six_degrees = true,
+            "--six-degrees-hops" => {
+                let value = iter.next().ok_or("--six-degrees-hops requires a hop count")?;
+                config.six_degrees_hops = value.parse::<usize>()
+                    .map_err(|_| format!("Invalid hop count '{}' for --six-degrees-hops", value))?;
+                config.six_degrees = true;
+            }
+            "--parquet" => {
+                let path = iter.next().ok_or("--parquet requires a file path")?;
+                config.parquet = Some(path.clone());
+            }
+            "--avoid" => {
+                let path = iter.next().ok_or("--avoid requires a file path")?;
+                config.avoid = Some(path.clone());
+            }
+            "--checkpoint" => {
+                let path = iter.next().ok_or("--checkpoint requires a file path")?;
+                config.checkpoint = Some(path.clone());
+            }
+            "--checkpoint-every" => {
+                let value = iter.next().ok_or("--checkpoint-every requires a pair count")?;
+                config.checkpoint_every = value.parse::<usize>().ok().filter(|&n| n > 0)
+                    .ok_or(format!("Invalid pair count '{}' for --checkpoint-every", value))?;
+            }
+            "--resume" => {
+                let path = iter.next().ok_or("--resume requires a checkpoint file path")?;
+                config.resume = Some(path.clone());
+            }
+            "--both-directions" => config.both_directions = true,
+            "--directed" => config.directed = true,
+            "--landmark-strategy" => {
+                let value = iter.next().ok_or("--landmark-strategy requires random or peripheral")?;
+                config.landmark_strategy = match value.as_str() {
+                    "random" => LandmarkStrategy::Random,
+                    "peripheral" => LandmarkStrategy::Peripheral,
+                    other => return Err(format!("Unknown landmark strategy '{}' (expected random or peripheral)", other)),
+                };
+            }
+            "--component" => {
+                let value = iter.next().ok_or("--component requires largest or start")?;
+                config.component = match value.as_str() {
+                    "largest" => ComponentChoice::Largest,
+                    "start" => ComponentChoice::Start,
+                    other => return Err(format!("Unknown component '{}' (expected largest or start)", other)),
+                };
+            }
+            "--has-edge" => {
+                let mut vertex = || -> Result<usize, String> {
+                    let value = iter.next().ok_or("--has-edge requires two vertex IDs")?;
+                    value.parse::<usize>().map_err(|_| format!("Invalid vertex '{}' for --has-edge", value))
+                };
+                config.has_edge = Some((vertex()?, vertex()?));
+            }
+            "--compare-search" => {
+                let mut vertex = || -> Result<usize, String> {
+                    let value = iter.next().ok_or("--compare-search requires two vertex IDs")?;
+                    value.parse::<usize>().map_err(|_| format!("Invalid vertex '{}' for --compare-search", value))
+                };
+                config.compare_search = Some((vertex()?, vertex()?));
+            }
+            "--edge-criticality" => config.edge_criticality = true,
+            "--diameter" => config.diameter = true,
+            "--csr" => {
+                let prefix = iter.next().ok_or("--csr requires an output path prefix")?;
+                config.csr = Some(prefix.clone());
+            }
+            "--single-source-average" => {
+                let value = iter.next().ok_or("--single-source-average requires a vertex ID")?;
+                let v = value.parse::<usize>()
+                    .map_err(|_| format!("Invalid vertex '{}' for --single-source-average", value))?;
+                config.single_source_average = Some(v);
+            }
+            "--instability-threshold" => {
+                let value = iter.next().ok_or("--instability-threshold requires a fraction")?;
+                config.instability_threshold = value.parse::<f64>().ok().filter(|f| *f >= 0.0 && f.is_finite())
+                    .ok_or(format!("Invalid fraction '{}' for --instability-threshold", value))?;
+            }
+            "--mst" => {
+                let path = iter.next().ok_or("--mst requires a file path")?;
+                config.mst = Some(path.clone());
+            }
+            "--min-pair-distance" => {
+                let value = iter.next().ok_or("--min-pair-distance requires a hop count")?;
+                let d = value.parse::<usize>()
+                    .map_err(|_| format!("Invalid hop count '{}' for --min-pair-distance", value))?;
+                config.min_pair_distance = Some(d);
+            }
+            other => return Err(format!("Unknown argument '{}'", other)),
+        }
+    }
+    let filtered = config.exclude_same_level || config.min_pair_distance.is_some();
+    if (config.checkpoint.is_some() || config.resume.is_some()) && filtered {
+        return Err("--checkpoint/--resume cannot be combined with pair filters".to_string());
+    }
+    if config.exclude_same_level && config.min_pair_distance.is_some() {
+        return Err("--exclude-same-level cannot be combined with --min-pair-distance".to_string());
+    }
+    if config.directed {
+        // These options assume every edge can be walked both ways
+        let undirected_only = [
+            ("--checkpoint/--resume", config.checkpoint.is_some() || config.resume.is_some()),
+            ("--exclude-same-level", config.exclude_same_level),
+            ("--min-pair-distance", config.min_pair_distance.is_some()),
+            ("--both-directions", config.both_directions),
+            ("--two-core", config.two_core),
+            ("--line-graph", config.line_graph),
+            ("--remove-hubs", config.remove_hubs.is_some()),
+            ("--alt-landmarks", config.alt_landmarks.is_some()),
+            ("--modularity", config.modularity.is_some()),
+            ("--edge-criticality", config.edge_criticality),
+            ("--compare-search", config.compare_search.is_some()),
+            ("--probability-weights", config.probability_weights),
+            ("--weighted", config.weighted),
+            ("--mst", config.mst.is_some()),
+            ("--dump-graph-stats", config.dump_graph_stats),
+            ("--hash", config.hash),
+            ("--closeness-csv", config.closeness_csv.is_some()),
+            ("--radius-sample", config.radius_sample.is_some()),
+            ("--diameter", config.diameter),
+        ];
+        if let Some((flag, _)) = undirected_only.iter().find(|(_, set)| *set) {
+            return Err(format!("--directed cannot be combined with {}", flag));
+        }
+    }
+    if config.normalize_ids != IdNormalization::Exact && config.format != EdgeFormat::Names {
+        return Err("--normalize-ids requires --format names".to_string());
+    }
+    if config.samples == 0 && (config.checkpoint.is_some() || config.resume.is_some() || filtered) {
+        return Err("--samples 0 computes every pair and cannot be combined with checkpoints or pair filters".to_string());
+    }
+    if config.samples == 0 {
+        // The exact mode measures no sampled pairs for these analyses to use
+        let sampled_only = [
+            ("--six-degrees", config.six_degrees),
+            ("--bootstrap", config.bootstrap.is_some()),
+            ("--harmonic-mean", config.harmonic_mean),
+            ("--probability-weights", config.probability_weights),
+            ("--remove-hubs", config.remove_hubs.is_some()),
+            ("--alt-landmarks", config.alt_landmarks.is_some()),
+            ("--dump-pairs", config.dump_pairs.is_some()),
+            ("--avoid", config.avoid.is_some()),
+            ("--both-directions", config.both_directions),
+            ("--parquet", config.parquet.is_some()),
+            ("--edge-criticality", config.edge_criticality),
+        ];
+        if let Some((flag, _)) = sampled_only.iter().find(|(_, set)| *set) {
+            return Err(format!("--samples 0 measures no sampled pairs and cannot be combined with {}", flag));
+        }
+    }
+    if config.start_where.is_some() && config.attributes.is_none() {
+        return Err("--start-where requires --attributes".to_string());
+    }
+    if !config.union.is_empty() && (config.file.is_some() || config.probability_weights || config.mst.is_some() || config.weighted) {
+        return Err("--union cannot be combined with --file, --probability-weights, --mst or --weighted".to_string());
+    }
+    if config.both_directions && (config.two_core || config.line_graph) {
+        // The comparison rebuilds both graphs from the input edges, not the analyzed graph
+        return Err("--both-directions cannot be combined with --two-core or --line-graph".to_string());
+    }
+    if config.line_graph {
+        // These options re-read the input file or take original vertex IDs,
+        // which are not line-graph vertices
+        let original_graph_only = [
+            ("--probability-weights", config.probability_weights),
+            ("--mst", config.mst.is_some()),
+            ("--avoid", config.avoid.is_some()),
+            ("--modularity", config.modularity.is_some()),
+            ("--start-where", config.start_where.is_some()),
+            ("--attributes", config.attributes.is_some()),
+            ("--single-source-average", config.single_source_average.is_some()),
+            ("--compare-search", config.compare_search.is_some()),
+        ];
+        if let Some((flag, _)) = original_graph_only.iter().find(|(_, set)| *set) {
+            return Err(format!("--line-graph cannot be combined with {}", flag));
+        }
+    }
+    if config.edge_sample.is_some() {
+        // These options re-read every edge of the input file and would ignore the sample
+        let full_input_only = [
+            ("--probability-weights", config.probability_weights),
+            ("--mst", config.mst.is_some()),
+        ];
+        if let Some((flag, _)) = full_input_only.iter().find(|(_, set)| *set) {
+            return Err(format!("--edge-sample cannot be combined with {}", flag));
+        }
+    }
+    if config.weighted && (config.samples == 0 || config.line_graph || config.edge_sample.is_some()) {
+        return Err("--weighted cannot be combined with --samples 0, --line-graph or --edge-sample".to_string());
+    }
+    Ok(config)
+}
+
+// Returns a StdRng seeded with `seed`, or from OS entropy when there is none.
+fn seeded_rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    }
+}
+
+// Formats a run summary as one line of space-separated key=value fields.
+// The keys and their order are fixed so that log files stay easy to grep and parse.
+// Fields that are unknown or were not computed are written as NA.
+fn format_logline(summary: &RunSummary) -> String {
+    fn or_na<T: std::fmt::Display>(value: Option<T>) -> String {
+        value.map_or("NA".to_string(), |v| v.to_string())
+    }
+    let average = summary.average.map(|a| format!("{:.4}", a));
+    format!("file={} n={} m={} start={} visited={} pairs={} avg={} diameter={} seed={}",
+            summary.file, summary.vertices, summary.edges, or_na(summary.start.as_ref()),
+            summary.visited, summary.pairs, or_na(average), or_na(summary.diameter), or_na(summary.seed))
+}
+
+// Measures the ordered `pairs` (distance from the first vertex to the second)
+// in both the directed graph of `edges` and its symmetrized undirected version.
+// Returns (directed, undirected) estimates.
+fn directed_and_undirected_estimates(edges: &[(usize, usize)], n: usize,
+                                     pairs: &[(usize, usize)]) -> (DistanceEstimate, DistanceEstimate) {
+    let directed = distances_for_pairs(&Graph::with_directed_edges(edges, n), pairs.to_vec());
+    let undirected = distances_for_pairs(&Graph::with_vertices(edges, n), pairs.to_vec());
+    (directed, undirected)
+}
+
+// Keeps each edge independently with probability `p`, preserving input order.
+fn sample_edges<R: Rng>(edges: &[(usize, usize)], p: f64, rng: &mut R) -> Vec<(usize, usize)> {
+    edges.iter().copied().filter(|_| rng.gen_bool(p)).collect()
+}
+
+// Generates the cycle graph on `n` vertices: 0-1-...-(n-1)-0.
+fn cycle_graph(n: usize) -> Graph {
+    let edges: Vec<(usize, usize)> = (0..n).map(|i| (i, (i + 1) % n)).collect();
+    Graph::with_vertices(&edges, n)
+}
+
+// Runs every metric on the 5-cycle, whose properties are known analytically,
+// and returns each check's description together with whether it passed.
+fn run_selftest() -> Vec<(String, bool)> {
+    let graph = cycle_graph(5);
+    let vertices: Vec<usize> = (0..5).collect();
+    let mut rng = rand::thread_rng();
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+
+    let mut all_pairs = Vec::new();
+    for a in 0..5 {
+        for b in (a + 1)..5 {
+            all_pairs.push((a, b));
+        }
+    }
+    let estimate = distances_for_pairs(&graph, all_pairs);
+    let radius = estimate_radius(&graph, &vertices, 5, &Deadline::default(), &mut rng);
+    let closeness = compute_closeness(&graph, &Deadline::default(), &mut rng);
+    let stats = compute_graph_stats(&graph);
+    let growth: Vec<usize> = bfs_levels(&graph, 0).iter().map(|l| l.len()).collect();
+
+    vec![
+        ("5 vertices and 5 edges".to_string(), graph.n == 5 && graph.num_edges() == 5),
+        ("every eccentricity is 2".to_string(), graph.eccentricities(&vertices).iter().all(|&e| e == 2)),
+        ("diameter is 2".to_string(), graph.diameter(&vertices) == 2),
+        ("radius is 2".to_string(), radius.is_some_and(|r| r.radius == 2)),
+        ("average distance is 1.5".to_string(), estimate.average().is_some_and(|a| close(a, 1.5))),
+        ("harmonic mean is 4/3".to_string(), estimate.harmonic_mean().is_some_and(|h| close(h, 4.0 / 3.0))),
+        ("every closeness is 2/3".to_string(),
+         closeness.exact && closeness.values.iter().all(|&c| close(c, 2.0 / 3.0))),
+        ("one connected component".to_string(), stats.components == 1 && stats.largest_component == 5),
+        ("no triangles".to_string(), stats.triangles == 0),
+        ("2-core keeps every vertex".to_string(), k_core(&graph, 2).iter().all(|&k| k)),
+        ("growth from vertex 0 is 1, 2, 2".to_string(), growth == vec![1, 2, 2]),
+        ("modularity of one community is 0".to_string(), close(modularity(&graph, &[0; 5]), 0.0)),
+    ]
+}
+
+// Merges several edge lists into one graph whose inputs cannot collide: each
+// list's vertex IDs are shifted past the largest ID of the lists before it.
+// Returns the merged edges and, per input, its range of vertex IDs.
+fn union_edge_lists(lists: &[Vec<(usize, usize)>]) -> (Vec<(usize, usize)>, Vec<Range<usize>>) {
+    let mut merged = Vec::new();
+    let mut ranges = Vec::new();
+    let mut offset = 0;
+    for edges in lists {
+        let size = edges.iter().flat_map(|&(u, v)| [u, v]).max().map_or(0, |m| m + 1);
+        merged.extend(edges.iter().map(|&(u, v)| (u + offset, v + offset)));
+        ranges.push(offset..offset + size);
+        offset += size;
+    }
+    (merged, ranges)
+}
+
+// Reads an unweighted edge list from `path` in the given format. `normalize`
+// only applies to EdgeFormat::Names.
+fn read_edges(path: &str, format: EdgeFormat, normalize: IdNormalization) -> Result<Vec<(usize, usize)>, String> {
+    let no_edges = || format!("{}: no valid edges found", path);
+    match format {
+        EdgeFormat::Csv => read_edge_list(path),
+        EdgeFormat::Mtx => read_mtx_edge_list(path)
+            .map(|edges| edges.into_iter().map(|(u, v, _)| (u, v)).collect())
+            .ok_or_else(no_edges),
+        EdgeFormat::Names => read_named_edge_list(path, normalize)
+            .map(|named| named.edges)
+            .ok_or_else(no_edges),
+    }
+}
+
+// Reads a weighted edge list from `path` in the given format, with names
+// interned the same way as read_edges.
+fn read_weighted_edges(path: &str, format: EdgeFormat,
+                       normalize: IdNormalization) -> Result<Vec<(usize, usize, f64)>, String> {
+    let no_edges = || format!("{}: no valid edges found", path);
+    match format {
+        EdgeFormat::Csv => read_weighted_edge_list(path),
+        EdgeFormat::Mtx => read_mtx_edge_list(path).ok_or_else(no_edges),
+        EdgeFormat::Names => read_named_edge_list(path, normalize)
+            .map(|named| named.edges.into_iter().map(|(u, v)| (u, v, 1.0)).collect())
+            .ok_or_else(no_edges),
+    }
+}
+
+// Reads a Matrix Market coordinate file. Lines starting with '%' (including the
+// %%MatrixMarket banner) are comments. The first other line gives
+// "rows cols entries", and each following line an entry "row col [value]".
+// Indices are 1-based and converted to 0-based; entries outside the declared
+// dimensions are skipped. Pattern files without values get weight 1.0.
+// Returns None if the dimensions line is missing or no entries were found.
+fn read_mtx_edge_list(path: &str) -> Option<Vec<(usize, usize, f64)>> {
+    let file = File::open(path).ok()?;
+    let mut lines = std::io::BufReader::new(file).lines().map_while(Result::ok)
+        .filter(|l| !l.trim().is_empty() && !l.trim_start().starts_with('%'));
+
+    let dims: Vec<usize> = lines.next()?.split_whitespace().filter_map(|x| x.parse().ok()).collect();
+    if dims.len() < 2 {
+        return None;
+    }
+    let (rows, cols) = (dims[0], dims[1]);
+
+    let mut edges = Vec::new();
+    for line_str in lines {
+        let parts: Vec<&str> = line_str.split_whitespace().collect();
+        if parts.len() < 2 {
+            warn!("{}: skipping malformed entry {:?}", path, line_str);
+            continue;
+        }
+        let weight = match parts.get(2) {
+            Some(w) => w.parse::<f64>().ok(),
+            None => Some(1.0),
+        };
+        match (parts[0].parse::<usize>(), parts[1].parse::<usize>(), weight) {
+            (Ok(r), Ok(c), Some(w)) if (1..=rows).contains(&r) && (1..=cols).contains(&c) => {
+                edges.push((r - 1, c - 1, w));
+            }
+            _ => warn!("{}: skipping malformed entry {:?}", path, line_str),
+        }
+    }
+    if edges.is_empty() { None } else { Some(edges) }
+}
+
+// Reads "a,b" edges whose endpoints are string names, interning the distinct
+// (normalized) names as vertex indices in sorted order, so the numbering does
+// not depend on the order of the lines. The first line is a header.
+fn read_named_edge_list(path: &str, normalize: IdNormalization) -> Option<NamedEdges> {
+    let file = File::open(path).ok()?;
+    let mut lines = std::io::BufReader::new(file).lines();
+    lines.next();
+
+    let mut raw_names: HashSet<String> = HashSet::new();
+    let mut named_edges = Vec::new();
+    for (line_no, line_str) in (2..).zip(lines.map_while(Result::ok)) {
+        if line_str.trim().is_empty() {
+            continue;
+        }
+        let parts: Vec<&str> = line_str.split(',').collect();
+        let keys: Vec<String> = parts.iter()
+            .map(|raw| match normalize {
+                IdNormalization::Exact => raw.to_string(),
+                IdNormalization::Trim => raw.trim().to_string(),
+                IdNormalization::Lowercase => raw.trim().to_lowercase(),
+            })
+            .collect();
+        if keys.len() != 2 || keys.iter().any(|k| k.is_empty()) {
+            warn!("{}:{}: skipping malformed line {:?}", path, line_no, line_str);
+            continue;
+        }
+
+        raw_names.extend(parts.iter().map(|raw| raw.to_string()));
+        named_edges.push((keys[0].clone(), keys[1].clone()));
+    }
+
+    let mut names: Vec<String> = named_edges.iter().flat_map(|(a, b)| [a.clone(), b.clone()]).collect();
+    names.sort_unstable();
+    names.dedup();
+    let index: HashMap<&str, usize> = names.iter().enumerate().map(|(v, name)| (name.as_str(), v)).collect();
+    let edges: Vec<(usize, usize)> = named_edges.iter()
+        .map(|(a, b)| (index[a.as_str()], index[b.as_str()]))
+        .collect();
+    debug!("read {} edges between {} named vertices from {}", edges.len(), names.len(), path);
+    if edges.is_empty() {
+        return None;
+    }
+    let merged = raw_names.len() - names.len();
+    Some(NamedEdges { edges, names, merged })
+}
+
+// Reads an edge list from a file specified by `path`.
+// Each line should be "u v" where u and v are integers, separated by a comma,
+// spaces or tabs. A third weight column is allowed and ignored here (see
+// read_weighted_edge_list). Blank lines and lines starting with '#' are ignored,
+// and the first remaining line is skipped as a header only if it is not an edge.
+// Returns an error naming the first malformed line, or if no edges were found.
+fn read_edge_list(path: &str) -> Result<Vec<(usize, usize)>, String> {
+    let edges = read_delimited_lines(path, |fields| match fields {
+        [a, b] => Some((a.parse().ok()?, b.parse().ok()?)),
+        [a, b, w] => {
+            w.parse::<f64>().ok()?;
+            Some((a.parse().ok()?, b.parse().ok()?))
+        }
+        _ => None,
+    })?;
+    debug!("read {} edges from {}", edges.len(), path);
+    Ok(edges)
+}
+
+// Splits a line of a delimited edge file into its non-empty fields. Commas,
+// spaces and tabs all act as separators, so "1,2", "1 2" and "1\t2" agree.
+fn split_edge_fields(line: &str) -> Vec<&str> {
+    line.split(|c: char| c == ',' || c.is_whitespace()).filter(|f| !f.is_empty()).collect()
+}
+
+// Shared line loop for the delimited edge readers: applies `parse` to the
+// fields of every non-blank, non-comment line. The first such line may be a
+// header and is skipped if it does not parse; any later line that does not
+// parse is an error reporting its line number and content.
+fn read_delimited_lines<T>(path: &str, parse: impl Fn(&[&str]) -> Option<T>) -> Result<Vec<T>, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut records = Vec::new();
+    let mut first = true;
+    for (line_no, line) in (1..).zip(std::io::BufReader::new(file).lines()) {
+        let line_str = line.map_err(|e| format!("{}:{}: {}", path, line_no, e))?;
+        let trimmed = line_str.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        match parse(&split_edge_fields(trimmed)) {
+            Some(record) => records.push(record),
+            None if first => debug!("{}:{}: skipping header {:?}", path, line_no, line_str),
+            None => return Err(format!("{}:{}: malformed edge line {:?}", path, line_no, line_str)),
+        }
+        first = false;
+    }
+    if records.is_empty() {
+        return Err(format!("{}: no edges found", path));
+    }
+    Ok(records)
+}
+
+// Reads vertex attributes from a CSV file whose header names the columns, e.g.
+// "vertex,category,country". The first column holds the vertex ID; rows whose
+// ID does not parse are skipped. Returns None if the file has no data rows.
+fn read_attributes(path: &str) -> Option<VertexAttributes> {
+    let file = File::open(path).ok()?;
+    let mut lines = std::io::BufReader::new(file).lines().map_while(Result::ok);
+    let header = lines.next()?;
+    let columns: Vec<String> = header.split(',').skip(1).map(|c| c.trim().to_string()).collect();
+
+    let mut values = HashMap::new();
+    for line_str in lines {
+        let mut parts = line_str.split(',').map(|p| p.trim());
+        if let Some(Ok(v)) = parts.next().map(|id| id.parse::<usize>()) {
+            values.insert(v, parts.map(|p| p.to_string()).collect());
+        }
+    }
+    if values.is_empty() { None } else { Some(VertexAttributes { columns, values }) }
+}
+
+// Among vertices whose attribute `key` equals `value`, returns the one with the
+// highest degree (ties broken by lower index). None if no vertex in the graph matches.
+fn choose_start_where(graph: &Graph, attributes: &VertexAttributes, key: &str, value: &str) -> Option<usize> {
+    attributes.values.keys().copied()
+        .filter(|&v| v < graph.n && attributes.get(v, key) == Some(value))
+        .max_by(|&a, &b| graph.degree(a).cmp(&graph.degree(b)).then(b.cmp(&a)))
+}
+
+// Returns `preferred` if it has a neighbor other than itself, otherwise the first
+// vertex that does. Returns None when no vertex has such a neighbor.
+fn choose_start_vertex(graph: &Graph, preferred: usize) -> Option<usize> {
+    let has_neighbor = |v: usize| graph.adjacency[v].iter().any(|&u| u != v);
+    if preferred < graph.n && has_neighbor(preferred) {
+        return Some(preferred);
+    }
+    (0..graph.n).find(|&v| has_neighbor(v))
+}
+
+// Reads a weighted edge list with lines "u v w", delimited like read_edge_list.
+// Lines with only "u v" get weight 1.0.
+fn read_weighted_edge_list(path: &str) -> Result<Vec<(usize, usize, f64)>, String> {
+    let edges = read_delimited_lines(path, |fields| match fields {
+        [a, b] => Some((a.parse().ok()?, b.parse().ok()?, 1.0)),
+        [a, b, w] => Some((a.parse().ok()?, b.parse().ok()?, w.parse().ok()?)),
+        _ => None,
+    })?;
+    debug!("read {} weighted edges from {}", edges.len(), path);
+    Ok(edges)
+}
+
+// Converts an edge probability in (0,1] into the additive cost -ln(p), so that
+// the cheapest path is the one whose probabilities multiply to the largest value.
+fn probability_to_cost(p: f64) -> Result<f64, String> {
+    if p > 0.0 && p <= 1.0 {
+        Ok(-p.ln())
+    } else {
+        Err(format!("Edge weight {} is not a probability in (0,1]", p))
+    }
+}
+
+// Disjoint-set forest with path compression and union by rank
+struct UnionFind {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+}
+
+impl UnionFind {
+    fn new(n: usize) -> UnionFind {
+        UnionFind { parent: (0..n).collect(), rank: vec![0; n] }
+    }
+
+    // Returns the representative of the set containing `v`
+    fn find(&mut self, v: usize) -> usize {
+        let mut root = v;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut current = v;
+        while self.parent[current] != root {
+            let next = self.parent[current];
+            self.parent[current] = root;
+            current = next;
+        }
+        root
+    }
+
+    // Merges the sets containing `a` and `b`. Returns false if already merged.
+    fn union(&mut self, a: usize, b: usize) -> bool {
+        let (ra, rb) = (self.find(a), self.find(b));
+        if ra == rb {
+            return false;
+        }
+        match self.rank[ra].cmp(&self.rank[rb]) {
+            CmpOrdering::Less => self.parent[ra] = rb,
+            CmpOrdering::Greater => self.parent[rb] = ra,
+            CmpOrdering::Equal => {
+                self.parent[rb] = ra;
+                self.rank[ra] += 1;
+            }
+        }
+        true
+    }
+}
+
+// Computes a minimum spanning forest with Kruskal's algorithm: edges are taken
+// in increasing weight order whenever they join two different trees. Disconnected
+// graphs get one tree per component. Returns the chosen edges and their total weight.
+fn minimum_spanning_forest(n: usize, edges: &[(usize, usize, f64)]) -> (Vec<(usize, usize, f64)>, f64) {
+    let mut sorted: Vec<(usize, usize, f64)> = edges.iter().copied().filter(|&(u, v, _)| u < n && v < n).collect();
+    sorted.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+    let mut sets = UnionFind::new(n);
+    let mut forest = Vec::new();
+    let mut total_weight = 0.0;
+    for (u, v, w) in sorted {
+        if sets.union(u, v) {
+            forest.push((u, v, w));
+            total_weight += w;
+        }
+    }
+    (forest, total_weight)
+}
+
+// Writes one "u,v,weight" row per edge to `path`, with a header line.
+fn write_weighted_edges_csv<T: std::fmt::Display>(path: &str, edges: &[(T, T, f64)]) -> std::io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "u,v,weight")?;
+    for (u, v, w) in edges {
+        writeln!(out, "{},{},{}", u, v, w)?;
+    }
+    out.flush()
+}
+
+// Reads edge weights from `path` and returns the mean Dijkstra distance over
+// the connected members of `pairs` and how many there were (None if no pair is
+// connected). Edges without a weight count as 1.0, so an unweighted file gives
+// the same average as BFS. Errors if the file cannot be read or has a negative weight.
+fn weighted_average_distance(path: &str, format: EdgeFormat, normalize: IdNormalization, ids: &VertexMap,
+                             pairs: &[(usize, usize)]) -> Result<Option<(f64, usize)>, String> {
+    let edges = read_weighted_edges(path, format, normalize)?;
+    let mut dense = Vec::with_capacity(edges.len());
+    for &(u, v, w) in &edges {
+        let (Some(a), Some(b)) = (ids.index_of(u), ids.index_of(v)) else {
+            continue;
+        };
+        if !(w >= 0.0 && w.is_finite()) {
+            return Err(format!("Edge weight {} is not a non-negative number (edge {},{})", w, u, v));
+        }
+        dense.push((a, b, w));
+    }
+    let graph = WeightedGraph::with_vertices(&dense, ids.len());
+
+    let distances: Vec<f64> = pairs.iter().filter_map(|&(a, b)| graph.dijkstra(a, b)).collect();
+    if distances.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some((distances.iter().sum::<f64>() / distances.len() as f64, distances.len())))
+}
+
+// Prints the weighted_average_distance of `pairs`.
+fn report_weighted_average(path: &str, format: EdgeFormat, normalize: IdNormalization, ids: &VertexMap,
+                           pairs: &[(usize, usize)]) {
+    match weighted_average_distance(path, format, normalize, ids, pairs) {
+        Ok(Some((average, count))) => {
+            report!("- Estimated average weighted shortest path distance over {} pairs: {:.4}", count, average)
+        }
+        Ok(None) => report!("- No sampled pair has a weighted path."),
+        Err(e) => error!("Could not compute weighted distances: {}", e),
+    }
+}
+
+// Reads edge weights from `path` as probabilities and prints the mean probability
+// of the most-probable path between each of `pairs`.
+fn report_most_probable_paths(path: &str, format: EdgeFormat, normalize: IdNormalization, ids: &VertexMap,
+                              pairs: &[(usize, usize)]) {
+    let edges = match read_weighted_edges(path, format, normalize) {
+        Ok(e) => e,
+        Err(e) => {
+            error!("Could not read a weighted edge list: {}", e);
+            return;
+        }
+    };
+    let mut cost_edges = Vec::with_capacity(edges.len());
+    for &(u, v, w) in &edges {
+        let (Some(a), Some(b)) = (ids.index_of(u), ids.index_of(v)) else {
+            continue;
+        };
+        match probability_to_cost(w) {
+            Ok(cost) => cost_edges.push((a, b, cost)),
+            Err(msg) => {
+                error!("{} (edge {},{}).", msg, u, v);
+                return;
+            }
+        }
+    }
+    let graph = WeightedGraph::with_vertices(&cost_edges, ids.len());
+
+    let costs: Vec<f64> = pairs.iter().filter_map(|&(a, b)| graph.dijkstra(a, b)).collect();
+    if costs.is_empty() {
+        report!("- No sampled pair has a most-probable path.");
+        return;
+    }
+    let mean_probability = costs.iter().map(|c| (-c).exp()).sum::<f64>() / costs.len() as f64;
+    let mean_cost = costs.iter().sum::<f64>() / costs.len() as f64;
+    report!("- Most-probable paths for {} pairs: mean probability {:.6} (mean -log distance {:.4})",
+            costs.len(), mean_probability, mean_cost);
+}
+
+// Computes a canonical 64-bit FNV-1a hash of the graph's edge set. Edges are
+// visited as (u,v) with u <= v in sorted order, so the hash does not depend on
+// the order or direction in which edges were listed in the input. Endpoints are
+// hashed as their original file IDs from `ids`, so relabeling a vertex changes it.
+fn graph_hash(graph: &Graph, ids: &VertexMap) -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+    let mut hash = FNV_OFFSET;
+    for (u, neighbors) in graph.adjacency.iter().enumerate() {
+        for &v in neighbors.iter().filter(|&&v| u <= v) {
+            // Dense IDs are assigned in sorted order, so original IDs keep u <= v
+            let (a, b) = ids.original_pair((u, v));
+            for byte in (a as u64).to_le_bytes().into_iter().chain((b as u64).to_le_bytes()) {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(FNV_PRIME);
+            }
+        }
+    }
+    hash
+}
+
+// Computes vertex and edge counts, density, degree statistics, components,
+// isolated vertices, leaves and the triangle count.
+fn compute_graph_stats(graph: &Graph) -> GraphStats {
+    let mut degrees: Vec<usize> = (0..graph.n).map(|v| graph.degree(v)).collect();
+    degrees.sort();
+    let edges = graph.num_edges();
+    let components = graph.connected_components();
+
+    let median_degree = match degrees.len() {
+        0 => 0.0,
+        len if len % 2 == 1 => degrees[len / 2] as f64,
+        len => (degrees[len / 2 - 1] + degrees[len / 2]) as f64 / 2.0,
+    };
+    let possible_edges = graph.n * graph.n.saturating_sub(1) / 2;
+
+    GraphStats {
+        vertices: graph.n,
+        edges,
+        density: if possible_edges == 0 { 0.0 } else { edges as f64 / possible_edges as f64 },
+        min_degree: degrees.first().copied().unwrap_or(0),
+        max_degree: degrees.last().copied().unwrap_or(0),
+        mean_degree: if graph.n == 0 { 0.0 } else { 2.0 * edges as f64 / graph.n as f64 },
+        median_degree,
+        components: components.len(),
+        largest_component: components.iter().map(|c| c.len()).max().unwrap_or(0),
+        isolated: degrees.iter().filter(|&&d| d == 0).count(),
+        leaves: degrees.iter().filter(|&&d| d == 1).count(),
+        triangles: triangle_count_fast(graph),
+    }
+}
+
+// Counts triangles by orienting every edge from the endpoint of lower degree to
+// the one of higher degree (ties broken by index) and intersecting out-neighbor
+// sets. Each triangle is found exactly once, and every vertex has at most
+// O(sqrt(m)) out-neighbors, which keeps hub vertices cheap.
+fn triangle_count_fast(graph: &Graph) -> u64 {
+    let rank = |v: usize| (graph.degree(v), v);
+    let out: Vec<Vec<usize>> = (0..graph.n)
+        .map(|u| graph.adjacency[u].iter().copied().filter(|&v| rank(u) < rank(v)).collect())
+        .collect();
+
+    let mut marked = vec![false; graph.n];
+    let mut triangles = 0u64;
+    for u in 0..graph.n {
+        for &v in &out[u] {
+            marked[v] = true;
+        }
+        for &v in &out[u] {
+            triangles += out[v].iter().filter(|&&w| marked[w]).count() as u64;
+        }
+        for &v in &out[u] {
+            marked[v] = false;
+        }
+    }
+    triangles
+}
+
+// Formats graph statistics as one report line per metric
+fn format_graph_stats(stats: &GraphStats) -> Vec<String> {
+    vec![
+        format!("- Vertices: {}", stats.vertices),
+        format!("- Edges: {}", stats.edges),
+        format!("- Density: {:.6}", stats.density),
+        format!("- Degree: min {}, max {}, mean {:.4}, median {:.1}",
+                stats.min_degree, stats.max_degree, stats.mean_degree, stats.median_degree),
+        format!("- Connected components: {} (largest has {} vertices)",
+                stats.components, stats.largest_component),
+        format!("- Isolated vertices: {}", stats.isolated),
+        format!("- Leaves (degree 1): {}", stats.leaves),
+        format!("- Triangles: {}", stats.triangles),
+    ]
+}
+
+// Reads a community assignment file with "vertex,community" lines, where the
+// community is any label and vertices use the edge file's IDs. Lines whose vertex
+// does not parse (e.g. a header) or is not in `ids` are skipped. Vertices that are
+// not listed get a singleton community of their own.
+// Returns a community index per dense vertex, or None if the file has no assignments.
+fn read_communities(path: &str, ids: &VertexMap) -> Option<Vec<usize>> {
+    let file = File::open(path).ok()?;
+    let mut labels: HashMap<String, usize> = HashMap::new();
+    let mut community_of = vec![usize::MAX; ids.len()];
+    for line_str in std::io::BufReader::new(file).lines().map_while(Result::ok) {
+        let parts: Vec<&str> = line_str.trim().split(',').collect();
+        if parts.len() != 2 {
+            continue;
+        }
+        if let Some(v) = parts[0].trim().parse::<usize>().ok().and_then(|id| ids.index_of(id)) {
+            let next = labels.len();
+            community_of[v] = *labels.entry(parts[1].trim().to_string()).or_insert(next);
+        }
+    }
+    if labels.is_empty() {
+        return None;
+    }
+
+    let mut next = labels.len();
+    for c in &mut community_of {
+        if *c == usize::MAX {
+            *c = next;
+            next += 1;
+        }
+    }
+    Some(community_of)
+}
+
+// Computes Newman's modularity of a partition:
+// Q = sum over communities c of (L_c / m) - (d_c / 2m)^2,
+// where L_c is the number of edges inside c and d_c the total degree of c.
+// A single community gives 0; graphs without edges also return 0.
+fn modularity(graph: &Graph, community_of: &[usize]) -> f64 {
+    let m = graph.num_edges();
+    if m == 0 {
+        return 0.0;
+    }
+    let communities = community_of.iter().max().map_or(0, |&c| c + 1);
+    let mut internal = vec![0usize; communities];
+    let mut degree_sum = vec![0usize; communities];
+    for (u, v) in graph.edges_iter() {
+        if community_of[u] == community_of[v] {
+            internal[community_of[u]] += 1;
+        }
+        degree_sum[community_of[u]] += 1;
+        degree_sum[community_of[v]] += 1;
+    }
+
+    let m = m as f64;
+    internal.iter().zip(&degree_sum)
+        .map(|(&l, &d)| l as f64 / m - (d as f64 / (2.0 * m)).powi(2))
+        .sum()
+}
+
+// Reads vertex IDs, one per line, into a membership mask over the dense vertices
+// of `ids`. Lines that do not parse (e.g. a header) and unknown IDs are skipped.
+fn read_vertex_set(path: &str, ids: &VertexMap) -> Option<Vec<bool>> {
+    let file = File::open(path).ok()?;
+    let mut members = vec![false; ids.len()];
+    for line_str in std::io::BufReader::new(file).lines().map_while(Result::ok) {
+        if let Some(v) = line_str.trim().parse::<usize>().ok().and_then(|id| ids.index_of(id)) {
+            members[v] = true;
+        }
+    }
+    Some(members)
+}
+
+// Like shortest_path, but BFS never enters a vertex with `avoid[v] == true`.
+// Returns usize::MAX if no path avoids them, including when an endpoint is avoided.
+fn shortest_path_avoiding(graph: &Graph, start: usize, end: usize, avoid: &[bool]) -> usize {
+    if start >= graph.n || end >= graph.n || avoid[start] || avoid[end] {
+        return usize::MAX;
+    }
+    if start == end {
+        return 0;
+    }
+
+    let mut distances = vec![usize::MAX; graph.n];
+    let mut queue = VecDeque::new();
+    distances[start] = 0;
+    queue.push_back(start);
+
+    while let Some(current) = queue.pop_front() {
+        for &neighbor in &graph.adjacency[current] {
+            if !avoid[neighbor] && distances[neighbor] == usize::MAX {
+                distances[neighbor] = distances[current] + 1;
+                if neighbor == end {
+                    return distances[end];
+                }
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    usize::MAX
+}
+
+// Recomputes the sampled pair distances with the `avoid` vertices removed from
+// every path, and prints the new average and how many pairs it disconnected.
+// Pairs with an avoided endpoint are left out and reported separately.
+fn report_avoidance(graph: &Graph, estimate: &DistanceEstimate, avoid: &[bool]) {
+    let mut pairs = Vec::new();
+    let mut distances = Vec::new();
+    let mut skipped = 0;
+    let mut disconnected = 0;
+    for (&(a, b), &before) in estimate.pairs.iter().zip(&estimate.distances) {
+        if avoid[a] || avoid[b] {
+            skipped += 1;
+            continue;
+        }
+        let after = shortest_path_avoiding(graph, a, b, avoid);
+        if before != usize::MAX && after == usize::MAX {
+            disconnected += 1;
+        }
+        pairs.push((a, b));
+        distances.push(after);
+    }
+    let constrained = DistanceEstimate { pairs, distances };
+
+    report!("\n- Avoiding {} vertices:", avoid.iter().filter(|&&x| x).count());
+    match constrained.average() {
+        Some(avg) => report!("- Average distance over {} pairs: {:.4}", constrained.counted_pairs(), avg),
+        None => report!("- No sampled pair is reachable without the avoided vertices."),
+    }
+    report!("- Pairs made unreachable by the avoidance: {}", disconnected);
+    report!("- Pairs skipped because an endpoint is avoided: {}", skipped);
+}
+
+// Graph::shortest_path with the usize::MAX sentinel for unreachable pairs, the
+// form DistanceEstimate stores.
+fn shortest_path(graph: &Graph, start: usize, end: usize) -> usize {
+    graph.shortest_path(start, end).unwrap_or(usize::MAX)
+}
+
+// Randomly selects up to `sample_size` distinct pairs from `vertices`.
+// Pairs are stored as (a,b) with a<b to avoid duplicates like (b,a).
+// Gives up after sample_size * 100 attempts, so fewer pairs may be returned.
+// Asking for at least every pair returns all of them without sampling.
+fn sample_pairs<R: Rng>(vertices: &[usize], sample_size: usize, rng: &mut R) -> Vec<(usize, usize)> {
+    sample_pairs_where(vertices, sample_size, rng, |_, _| true).0
+}
+
+// Like sample_pairs, but the pairs are ordered: (a,b) and (b,a) are different
+// pairs, as they are in a directed graph. Pairs still have a != b.
+fn sample_ordered_pairs<R: Rng>(vertices: &[usize], sample_size: usize, rng: &mut R) -> Vec<(usize, usize)> {
+    sample_pairs_with(vertices, sample_size, rng, true, |_, _| true).0
+}
+
+// Like sample_pairs, but only keeps pairs for which `keep(a, b)` is true.
+// Returns the kept pairs and the number of distinct candidate pairs rejected.
+fn sample_pairs_where<R, F>(vertices: &[usize], sample_size: usize, rng: &mut R,
+                            keep: F) -> (Vec<(usize, usize)>, usize)
+where
+    R: Rng,
+    F: FnMut(usize, usize) -> bool,
+{
+    sample_pairs_with(vertices, sample_size, rng, false, keep)
+}
+
+// Shared sampler: with `ordered`, pairs keep the order they were drawn in
+// instead of being normalized to a<b.
+fn sample_pairs_with<R, F>(vertices: &[usize], sample_size: usize, rng: &mut R, ordered: bool,
+                           mut keep: F) -> (Vec<(usize, usize)>, usize)
+where
+    R: Rng,
+    F: FnMut(usize, usize) -> bool,
+{
+    let mut random_pairs = Vec::new();
+    let mut rejected = 0;
+    if vertices.len() < 2 {
+        return (random_pairs, rejected);
+    }
+
+    // Random draws would only keep repeating once every pair has been seen
+    if sample_size >= pair_count(vertices.len(), ordered) {
+        for pair in all_pairs(vertices, ordered) {
+            if keep(pair.0, pair.1) {
+                random_pairs.push(pair);
+            } else {
+                rejected += 1;
+            }
+        }
+        return (random_pairs, rejected);
+    }
+
+    let vertex_count = vertices.len();
+    let mut chosen_pairs = HashSet::new();
+    let max_attempts = sample_size.saturating_mul(100);
+    let mut attempts = 0;
+
+    while random_pairs.len() < sample_size && attempts < max_attempts {
+        let i = rng.gen_range(0..vertex_count);
+        let j = rng.gen_range(0..vertex_count);
+
+        if i != j {
+            let a = vertices[i];
+            let b = vertices[j];
+            let ordered_pair = if a < b || ordered { (a,b) } else { (b,a) };
+
+            // Rejected pairs are remembered too, so each is only counted once
+            if chosen_pairs.insert(ordered_pair) {
+                if keep(a, b) {
+                    random_pairs.push(ordered_pair);
+                } else {
+                    rejected += 1;
+                }
+            }
+        }
+        attempts += 1;
+    }
+    (random_pairs, rejected)
+}
+
+// Number of distinct pairs of `k` vertices: C(k,2), or k(k-1) when `ordered`.
+// Saturates instead of overflowing.
+fn pair_count(k: usize, ordered: bool) -> usize {
+    let ordered_count = k.saturating_mul(k.saturating_sub(1));
+    if ordered { ordered_count } else { ordered_count / 2 }
+}
+
+// Every distinct pair of `vertices`, as (a,b) with a<b unless `ordered`.
+fn all_pairs(vertices: &[usize], ordered: bool) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    for (i, &a) in vertices.iter().enumerate() {
+        for (j, &b) in vertices.iter().enumerate() {
+            if ordered && i != j {
+                pairs.push((a, b));
+            } else if !ordered && i < j {
+                pairs.push((a.min(b), a.max(b)));
+            }
+        }
+    }
+    pairs
+}
+
+// Samples pairs whose shortest path distance is at least `min_distance`. Each
+// candidate's distance is computed before deciding to keep it; unreachable
+// candidates are rejected. Returns the estimate and the number of rejected candidates.
+fn sample_far_pairs<R: Rng>(graph: &Graph, vertices: &[usize], sample_size: usize, min_distance: usize,
+                            rng: &mut R) -> (DistanceEstimate, usize) {
+    let mut distances = Vec::new();
+    let (pairs, rejected) = sample_pairs_where(vertices, sample_size, rng, |a, b| {
+        let d = shortest_path(graph, a, b);
+        let keep = d != usize::MAX && d >= min_distance;
+        if keep {
+            distances.push(d);
+        }
+        keep
+    });
+    (DistanceEstimate { pairs, distances }, rejected)
+}
+
+// Computes the shortest path distance of each pair.
+fn distances_for_pairs(graph: &Graph, pairs: Vec<(usize, usize)>) -> DistanceEstimate {
+    let distances = pairs.iter().map(|&(a, b)| shortest_path(graph, a, b)).collect();
+    DistanceEstimate { pairs, distances }
+}
+
+// Samples pairs from `vertices` and computes the shortest path distance of each.
+fn estimate_average_distance<R: Rng>(graph: &Graph, vertices: &[usize], sample_size: usize,
+                                     rng: &mut R) -> DistanceEstimate {
+    distances_for_pairs(graph, sample_pairs(vertices, sample_size, rng))
+}
+
+// Wall-clock limit shared by the per-vertex algorithms. The default has no limit.
+#[derive(Debug, Default, Clone, Copy)]
+struct Deadline {
+    at: Option<Instant>,
+}
+
+impl Deadline {
+    // A deadline `seconds` from now
+    fn after_seconds(seconds: f64) -> Deadline {
+        Deadline { at: Some(Instant::now() + Duration::from_secs_f64(seconds)) }
+    }
+
+    // True once the limit has passed
+    fn expired(&self) -> bool {
+        self.at.is_some_and(|at| Instant::now() >= at)
+    }
+}
+
+// Computes the k-core: repeatedly removes vertices with fewer than `k`
+// remaining neighbors until none are left. Returns true for vertices in the core.
+fn k_core(graph: &Graph, k: usize) -> Vec<bool> {
+    let mut degree: Vec<usize> = (0..graph.n).map(|v| graph.degree(v)).collect();
+    let mut keep = vec![true; graph.n];
+    let mut queue: VecDeque<usize> = (0..graph.n).filter(|&v| degree[v] < k).collect();
+    for &v in &queue {
+        keep[v] = false;
+    }
+
+    while let Some(v) = queue.pop_front() {
+        for &u in &graph.adjacency[v] {
+            if u != v && keep[u] {
+                degree[u] -= 1;
+                if degree[u] < k {
+                    keep[u] = false;
+                    queue.push_back(u);
+                }
+            }
+        }
+    }
+    keep
+}
+
+// Returns a warning when the sampled average is statistically unstable, i.e.
+// its standard error is more than `threshold` times the average. The message
+// includes the coefficient of variation of the sampled distances.
+fn instability_warning(estimate: &DistanceEstimate, threshold: f64) -> Option<String> {
+    let mean = estimate.average()?;
+    let sd = estimate.standard_deviation()?;
+    let se = estimate.standard_error()?;
+    if mean > 0.0 && se > threshold * mean {
+        Some(format!("the average is unstable: standard error {:.4} is {:.1}% of the mean \
+                      (coefficient of variation {:.3} over {} pairs). Raise --samples (or use --samples 0 for the exact average) before relying on it.",
+                     se, 100.0 * se / mean, sd / mean, estimate.counted_pairs()))
+    } else {
+        None
+    }
+}
+
+// Resamples `values` with replacement `resamples` times and summarizes the
+// spread of the resampled means: their standard deviation (the bootstrap
+// standard error) and a 95% percentile interval. None if `values` is empty.
+fn bootstrap_mean<R: Rng>(values: &[usize], resamples: usize, rng: &mut R) -> Option<Bootstrap> {
+    if values.is_empty() || resamples == 0 {
+        return None;
+    }
+    let mut means: Vec<f64> = (0..resamples)
+        .map(|_| {
+            let total: usize = (0..values.len()).map(|_| values[rng.gen_range(0..values.len())]).sum();
+            total as f64 / values.len() as f64
+        })
+        .collect();
+    means.sort_by(f64::total_cmp);
+
+    let mean = means.iter().sum::<f64>() / resamples as f64;
+    let variance = means.iter().map(|m| (m - mean).powi(2)).sum::<f64>() / (resamples - 1).max(1) as f64;
+    let percentile = |q: f64| means[((q * (resamples - 1) as f64).round() as usize).min(resamples - 1)];
+    Some(Bootstrap { standard_error: variance.sqrt(), lower: percentile(0.025), upper: percentile(0.975) })
+}
+
+// Computes Graph::eccentricities for a prefix of `vertices`, in parallel batches
+// that double in size up to ECCENTRICITY_BATCH_MAX. Stops after the batch during
+// which `deadline` passes, so the result may be shorter than `vertices`.
+fn eccentricities_until(graph: &Graph, vertices: &[usize], deadline: &Deadline) -> Vec<usize> {
+    let mut eccentricities = Vec::with_capacity(vertices.len());
+    let mut batch = 1;
+    while eccentricities.len() < vertices.len() {
+        let start = eccentricities.len();
+        let end = (start + batch).min(vertices.len());
+        eccentricities.extend(graph.eccentricities(&vertices[start..end]));
+        if deadline.expired() {
+            break;
+        }
+        batch = (batch * 2).min(ECCENTRICITY_BATCH_MAX);
+    }
+    eccentricities
+}
+
+// Estimates the radius of the component `vertices` from the eccentricities of
+// `k` randomly chosen members. The minimum found is an upper bound on the true
+// radius, and exact when k covers the whole component. Stops early once
+// `deadline` has passed. Returns None if no vertex was evaluated.
+fn estimate_radius<R: Rng>(graph: &Graph, vertices: &[usize], k: usize, deadline: &Deadline,
+                           rng: &mut R) -> Option<RadiusEstimate> {
+    let sample: Vec<usize> = vertices.choose_multiple(rng, k).copied().collect();
+    let eccentricities = eccentricities_until(graph, &sample, deadline);
+    let best = eccentricities.iter().copied().zip(sample.iter().copied()).min();
+    best.map(|(radius, vertex)| RadiusEstimate {
+        radius,
+        vertex,
+        sampled: eccentricities.len(),
+        timed_out: eccentricities.len() < sample.len(),
+    })
+}
+
+// Estimates the diameter of the component `vertices` with two BFS sweeps: from
+// a random member to its farthest vertex `from`, then from `from` to its
+// farthest vertex `to`. dist(from, to) is a lower bound on the diameter, exact
+// on trees and usually close on real networks. None if `vertices` is empty.
+fn double_sweep_diameter<R: Rng>(graph: &Graph, vertices: &[usize], rng: &mut R) -> Option<DoubleSweep> {
+    let farthest = |v: usize| {
+        bfs_distances(graph, v).into_iter().enumerate()
+            .filter(|&(_, d)| d != usize::MAX)
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+            .unwrap_or((v, 0))
+    };
+    let &random = vertices.choose(rng)?;
+    let (from, _) = farthest(random);
+    let (to, diameter) = farthest(from);
+    Some(DoubleSweep { diameter, from, to })
+}
+
+// Picks up to `k` distinct landmark vertices uniformly from `vertices`.
+fn select_random_landmarks<R: Rng>(vertices: &[usize], k: usize, rng: &mut R) -> Vec<usize> {
+    vertices.choose_multiple(rng, k).copied().collect()
+}
+
+// Computes the eccentricity of up to `k` randomly chosen members of `vertices`.
+// Returns (vertex, eccentricity) pairs in sampling order.
+fn sampled_eccentricities<R: Rng>(graph: &Graph, vertices: &[usize], k: usize,
+                                  rng: &mut R) -> Vec<(usize, usize)> {
+    let sample: Vec<usize> = vertices.choose_multiple(rng, k).copied().collect();
+    let eccentricities = graph.eccentricities(&sample);
+    sample.into_iter().zip(eccentricities).collect()
+}
+
+// Picks `k` landmarks on the periphery of the component: samples
+// PERIPHERAL_CANDIDATES_PER_LANDMARK * k candidates and keeps the `k` with the
+// highest eccentricity (ties broken by lower index).
+fn select_peripheral_landmarks<R: Rng>(graph: &Graph, vertices: &[usize], k: usize,
+                                       rng: &mut R) -> Vec<usize> {
+    let mut candidates = sampled_eccentricities(graph, vertices, k * PERIPHERAL_CANDIDATES_PER_LANDMARK, rng);
+    candidates.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    candidates.into_iter().take(k).map(|(v, _)| v).collect()
+}
+
+// Precomputes the BFS distances from every landmark.
+fn compute_landmarks(graph: &Graph, vertices: Vec<usize>) -> Landmarks {
+    let distances = vertices.iter().map(|&l| bfs_distances(graph, l)).collect();
+    Landmarks { vertices, distances }
+}
+
+impl Landmarks {
+    // Bounds d(a,b) using the triangle inequality through each landmark L:
+    // |d(L,a) - d(L,b)| <= d(a,b) <= d(L,a) + d(L,b).
+    // Returns (largest lower bound, smallest upper bound), or None if no
+    // landmark reaches both vertices.
+    fn bounds(&self, a: usize, b: usize) -> Option<(usize, usize)> {
+        let mut best: Option<(usize, usize)> = None;
+        for dist in &self.distances {
+            let (da, db) = (dist[a], dist[b]);
+            if da == usize::MAX || db == usize::MAX {
+                continue;
+            }
+            let lower = da.abs_diff(db);
+            let upper = da + db;
+            best = Some(match best {
+                Some((lo, up)) => (lo.max(lower), up.min(upper)),
+                None => (lower, upper),
+            });
+        }
+        best
+    }
+}
+
+// Averages the ALT lower bounds, upper bounds, midpoints and gaps over `pairs`.
+// Pairs that no landmark can bound are skipped. Returns None if none are bounded.
+fn alt_estimate(landmarks: &Landmarks, pairs: &[(usize, usize)]) -> Option<AltEstimate> {
+    let bounds: Vec<(usize, usize)> = pairs.iter().filter_map(|&(a, b)| landmarks.bounds(a, b)).collect();
+    if bounds.is_empty() {
+        return None;
+    }
+    let count = bounds.len() as f64;
+    let lower_sum: usize = bounds.iter().map(|b| b.0).sum();
+    let upper_sum: usize = bounds.iter().map(|b| b.1).sum();
+    Some(AltEstimate {
+        pairs: bounds.len(),
+        mean_lower: lower_sum as f64 / count,
+        mean_upper: upper_sum as f64 / count,
+        mean_midpoint: (lower_sum + upper_sum) as f64 / (2.0 * count),
+        mean_gap: (upper_sum - lower_sum) as f64 / count,
+    })
+}
+
+// Writes the pairs measured so far, with their running totals, to `path`.
+// Pairs are written by their original IDs from `ids`, so a checkpoint stays
+// valid however the vertices get renumbered on the next run.
+// The file is written next to `path` first and then renamed over it, so an
+// interruption never leaves a half-written checkpoint behind.
+fn write_checkpoint(path: &str, estimate: &DistanceEstimate, ids: &VertexMap) -> std::io::Result<()> {
+    let tmp_path = format!("{}.tmp", path);
+    {
+        let mut out = BufWriter::new(File::create(&tmp_path)?);
+        writeln!(out, "total_distance {}", estimate.total_distance())?;
+        writeln!(out, "counted_pairs {}", estimate.counted_pairs())?;
+        writeln!(out, "pairs {}", estimate.pairs.len())?;
+        for (&pair, &d) in estimate.pairs.iter().zip(&estimate.distances) {
+            let (a, b) = ids.original_pair(pair);
+            writeln!(out, "{},{},{}", a, b, format_distance(d, DistanceFormat::Csv))?;
+        }
+        out.flush()?;
+    }
+    std::fs::rename(tmp_path, path)
+}
+
+// Reads a checkpoint written by write_checkpoint, mapping its original IDs
+// back to the dense vertices of `ids`. Returns None if the file is missing,
+// malformed, refers to IDs that are not in `ids`, or its running totals do not
+// match the pairs it lists.
+fn read_checkpoint(path: &str, ids: &VertexMap) -> Option<DistanceEstimate> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    let mut lines = contents.lines();
+    let mut header = |key: &str| -> Option<usize> {
+        lines.next()?.strip_prefix(key)?.trim().parse().ok()
+    };
+    let total_distance = header("total_distance")?;
+    let counted_pairs = header("counted_pairs")?;
+    let pair_count = header("pairs")?;
+
+    let mut pairs = Vec::with_capacity(pair_count);
+    let mut distances = Vec::with_capacity(pair_count);
+    for line in lines {
+        let parts: Vec<&str> = line.trim().split(',').collect();
+        if parts.len() != 3 {
+            return None;
+        }
+        let (a, b) = (parts[0].parse::<usize>().ok()?, parts[1].parse::<usize>().ok()?);
+        let (a, b) = (ids.index_of(a)?, ids.index_of(b)?);
+        // Unreachable pairs have an empty distance ("-" in older checkpoints)
+        let d = match parts[2] {
+            "" | "-" => usize::MAX,
+            value => value.parse().ok()?,
+        };
+        pairs.push((a, b));
+        distances.push(d);
+    }
+
+    let estimate = DistanceEstimate { pairs, distances };
+    let consistent = estimate.pairs.len() == pair_count
+        && estimate.counted_pairs() == counted_pairs
+        && estimate.total_distance() == total_distance;
+    if consistent { Some(estimate) } else { None }
+}
+
+// Where and how often estimate_with_checkpoints saves its progress
+struct Checkpoints<'a> {
+    path: &'a str,
+    // Number of new pairs between checkpoints
+    every: usize,
+    ids: &'a VertexMap,
+}
+
+// Samples and measures pairs like estimate_average_distance, continuing from
+// `resumed` if given, and writes a checkpoint to `checkpoints.path` after every
+// `checkpoints.every` new pairs and once more at the end.
+fn estimate_with_checkpoints<R: Rng>(graph: &Graph, vertices: &[usize], sample_size: usize, rng: &mut R,
+                                     checkpoints: &Checkpoints,
+                                     resumed: Option<DistanceEstimate>) -> std::io::Result<DistanceEstimate> {
+    let Checkpoints { path, every, ids } = *checkpoints;
+    let mut estimate = resumed.unwrap_or(DistanceEstimate { pairs: Vec::new(), distances: Vec::new() });
+    let mut chosen_pairs: HashSet<(usize, usize)> = estimate.pairs.iter().copied().collect();
+    let max_attempts = sample_size.saturating_mul(100);
+    let mut attempts = 0;
+    let mut since_checkpoint = 0;
+
+    // Asking for at least every pair measures all the ones not yet resumed, in order
+    let mut remaining = if sample_size >= pair_count(vertices.len(), false) {
+        Some(all_pairs(vertices, false).into_iter())
+    } else {
+        None
+    };
+    while vertices.len() >= 2 && estimate.pairs.len() < sample_size && attempts < max_attempts {
+        let (a, b) = match remaining.as_mut() {
+            Some(pairs) => match pairs.next() {
+                Some(pair) => pair,
+                None => break,
+            },
+            None => {
+                let i = rng.gen_range(0..vertices.len());
+                let j = rng.gen_range(0..vertices.len());
+                attempts += 1;
+                if i == j {
+                    continue;
+                }
+                (vertices[i].min(vertices[j]), vertices[i].max(vertices[j]))
+            }
+        };
+        if chosen_pairs.insert((a, b)) {
+            estimate.pairs.push((a, b));
+            estimate.distances.push(shortest_path(graph, a, b));
+            since_checkpoint += 1;
+            if since_checkpoint == every {
+                write_checkpoint(path, &estimate, ids)?;
+                debug!("checkpointed {} pairs to {}", estimate.pairs.len(), path);
+                since_checkpoint = 0;
+            }
+        }
+    }
+    write_checkpoint(path, &estimate, ids)?;
+    Ok(estimate)
+}
+
+// Returns the `k` vertices with the highest degree, ties broken by lower index.
+fn top_degree_vertices(graph: &Graph, k: usize) -> Vec<usize> {
+    let mut vertices: Vec<usize> = (0..graph.n).collect();
+    vertices.sort_by(|&a, &b| graph.degree(b).cmp(&graph.degree(a)).then(a.cmp(&b)));
+    vertices.truncate(k);
+    vertices
+}
+
+// Returns a copy of the graph keeping only vertices with `keep[v] == true`.
+// Vertex indices are preserved; removed vertices remain as isolated entries.
+fn subgraph(graph: &Graph, keep: &[bool]) -> Graph {
+    let adjacency = graph.adjacency.iter().enumerate()
+        .map(|(v, neighbors)| {
+            if keep[v] {
+                neighbors.iter().copied().filter(|&u| keep[u]).collect()
+            } else {
+                Vec::new()
+            }
+        })
+        .collect();
+    Graph { n: graph.n, adjacency }
+}
+
+// Returns a copy of the graph without the undirected edge u-v.
+fn remove_edge(graph: &Graph, u: usize, v: usize) -> Graph {
+    let mut adjacency = graph.adjacency.clone();
+    adjacency[u].retain(|&w| w != v);
+    adjacency[v].retain(|&w| w != u);
+    Graph { n: graph.n, adjacency }
+}
+
+// Removes each edge in turn and re-measures the distances of the same `pairs`.
+// Edges are ranked by how many pairs their removal disconnects, then by how much
+// the average grows, most critical first.
+fn edge_criticality(graph: &Graph, pairs: &[(usize, usize)]) -> Vec<EdgeImpact> {
+    let base = distances_for_pairs(graph, pairs.to_vec());
+    let base_average = base.average().unwrap_or(0.0);
+    let mut impacts: Vec<EdgeImpact> = graph.edges_iter()
+        .map(|(u, v)| {
+            let after = distances_for_pairs(&remove_edge(graph, u, v), pairs.to_vec());
+            EdgeImpact {
+                edge: (u, v),
+                increase: after.average().map_or(f64::INFINITY, |avg| avg - base_average),
+                disconnected: after.unreachable_pairs() - base.unreachable_pairs(),
+            }
+        })
+        .collect();
+    impacts.sort_by(|a, b| b.disconnected.cmp(&a.disconnected)
+        .then(b.increase.total_cmp(&a.increase)));
+    impacts
+}
+
+// Builds the line graph: vertex i is the i-th edge of `graph` (in `edges_iter`
+// order), and two such vertices are adjacent when their edges share an endpoint.
+// Self-loops are not edges of `graph`, so they do not appear.
+fn line_graph(graph: &Graph) -> Graph {
+    let edges: Vec<(usize, usize)> = graph.edges_iter().collect();
+    let mut incident: Vec<Vec<usize>> = vec![Vec::new(); graph.n];
+    for (id, &(u, v)) in edges.iter().enumerate() {
+        incident[u].push(id);
+        incident[v].push(id);
+    }
+
+    // Every pair of edges meeting at a vertex becomes an edge of the line graph
+    let mut line_edges = Vec::new();
+    for ids in &incident {
+        for (k, &a) in ids.iter().enumerate() {
+            for &b in &ids[k + 1..] {
+                line_edges.push((a, b));
+            }
+        }
+    }
+    Graph::with_vertices(&line_edges, edges.len())
+}
+
+// Runs a full BFS from `start` and returns the distance to every vertex.
+// Unreachable vertices (and all vertices if `start` is invalid) get usize::MAX.
+fn bfs_distances(graph: &Graph, start: usize) -> Vec<usize> {
+    let mut distances = vec![usize::MAX; graph.n];
+    if start >= graph.n {
+        return distances;
+    }
+
+    let mut queue = VecDeque::new();
+    distances[start] = 0;
+    queue.push_back(start);
+
+    while let Some(current) = queue.pop_front() {
+        for &neighbor in &graph.adjacency[current] {
+            if distances[neighbor] == usize::MAX {
+                distances[neighbor] = distances[current] + 1;
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    distances
+}
+
+// Runs a BFS from `start` and groups the reached vertices by distance:
+// element d holds every vertex exactly d hops away. Empty if `start` is invalid.
+fn bfs_levels(graph: &Graph, start: usize) -> Vec<Vec<usize>> {
+    let mut levels: Vec<Vec<usize>> = Vec::new();
+    for (v, &d) in bfs_distances(graph, start).iter().enumerate() {
+        if d != usize::MAX {
+            if levels.len() <= d {
+                levels.resize(d + 1, Vec::new());
+            }
+            levels[d].push(v);
+        }
+    }
+    levels
+}
+
+// Writes the CSR arrays of the graph to `<prefix>_indptr.txt` and
+// `<prefix>_indices.txt`, one integer per line (readable with numpy.loadtxt).
+// Rows and indices are dense vertex indices; `<prefix>_ids.txt` gives the
+// original ID (or name) of each row so results can be mapped back to the input file.
+fn write_csr(prefix: &str, graph: &Graph, ids: &VertexMap) -> std::io::Result<()> {
+    let (indptr, indices) = graph.to_csr();
+    let original: Vec<String> = (0..graph.n).map(|v| ids.label(v)).collect();
+    let as_text = |values: &[usize]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+    for (suffix, values) in [("indptr", as_text(&indptr)), ("indices", as_text(&indices)), ("ids", original)] {
+        let mut out = BufWriter::new(File::create(format!("{}_{}.txt", prefix, suffix))?);
+        for value in values {
+            writeln!(out, "{}", value)?;
+        }
+        out.flush()?;
+    }
+    Ok(())
+}
+
+// Writes "radius,count_at_radius,cumulative_count" rows for BFS levels, with a header line.
+fn write_growth_csv(path: &str, levels: &[Vec<usize>]) -> std::io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "radius,count_at_radius,cumulative_count")?;
+    let mut cumulative = 0;
+    for (radius, level) in levels.iter().enumerate() {
+        cumulative += level.len();
+        writeln!(out, "{},{},{}", radius, level.len(), cumulative)?;
+    }
+    out.flush()
+}
+
+// Runs one BFS from `source` and returns the mean of its finite distances to
+// every other vertex, together with how many vertices it reaches (excluding
+// itself). None if `source` is invalid or reaches nothing.
+fn single_source_average(graph: &Graph, source: usize) -> Option<(f64, usize)> {
+    let reachable: Vec<usize> = bfs_distances(graph, source).into_iter()
+        .filter(|&d| d != usize::MAX && d > 0)
+        .collect();
+    if reachable.is_empty() {
+        return None;
+    }
+    let total: usize = reachable.iter().sum();
+    Some((total as f64 / reachable.len() as f64, reachable.len()))
+}
+
+// Exact mean distance from a histogram built by Graph::distance_counts_from.
+// Returns the mean and the number of pairs, or None if no source reaches
+// another vertex.
+fn exact_average_distance(histogram: &[u64]) -> Option<(f64, u64)> {
+    let total: u64 = histogram.iter().enumerate().map(|(d, &count)| d as u64 * count).sum();
+    let pairs: u64 = histogram.iter().sum();
+    if pairs == 0 { None } else { Some((total as f64 / pairs as f64, pairs)) }
+}
+
+// Prints how many pairs fall at each distance, followed by the median, 90th
+// percentile and maximum of the reachable distances and the unreachable count.
+fn report_distance_distribution(histogram: &[u64], unreachable: u64) {
+    report!("- Distance distribution:");
+    for (d, &count) in histogram.iter().enumerate() {
+        if count > 0 {
+            report!("    {}: {} pairs", d, count);
+        }
+    }
+    if let (Some(median), Some(p90)) = (histogram_percentile(histogram, 0.5), histogram_percentile(histogram, 0.9)) {
+        let max = histogram.iter().rposition(|&count| count > 0).unwrap_or(0);
+        report!("- Median distance: {}, 90th percentile: {}, maximum: {}", median, p90, max);
+    }
+    report!("- Unreachable pairs: {}", unreachable);
+}
+
+// Computes the closeness centrality of `vertex` within its component:
+// (reachable vertices - 1) / (sum of distances to them). Isolated vertices get 0.
+fn closeness_centrality(graph: &Graph, vertex: usize) -> f64 {
+    let distances = bfs_distances(graph, vertex);
+    let mut total = 0;
+    let mut reached = 0;
+    for &d in &distances {
+        if d != usize::MAX && d > 0 {
+            total += d;
+            reached += 1;
+        }
+    }
+    if total == 0 { 0.0 } else { reached as f64 / total as f64 }
+}
+
+// Computes closeness for every vertex by running BFS from a set of sources and
+// taking each vertex's inverse mean distance to the sources it can reach. Small
+// graphs use every vertex as a source, which gives the exact value; graphs above
+// CLOSENESS_EXACT_LIMIT use CLOSENESS_SAMPLE_SOURCES random sources instead.
+// Stops early (keeping what was computed) once `deadline` has passed.
+fn compute_closeness<R: Rng>(graph: &Graph, deadline: &Deadline, rng: &mut R) -> Closeness {
+    let mut sources: Vec<usize> = (0..graph.n).collect();
+    sources.shuffle(rng);
+    if graph.n > CLOSENESS_EXACT_LIMIT {
+        sources.truncate(CLOSENESS_SAMPLE_SOURCES);
+    }
+
+    let mut totals = vec![0usize; graph.n];
+    let mut reached = vec![0usize; graph.n];
+    let mut processed = 0;
+    for &s in &sources {
+        for (v, &d) in bfs_distances(graph, s).iter().enumerate() {
+            if d != usize::MAX && d > 0 {
+                totals[v] += d;
+                reached[v] += 1;
+            }
+        }
+        processed += 1;
+        if deadline.expired() {
+            break;
+        }
+    }
+    let timed_out = processed < sources.len();
+    info!("closeness: processed {} of {} BFS sources", processed, sources.len());
+
+    // Vertices whose component holds no processed source fall back to an exact BFS,
+    // unless the deadline has already passed
+    let values = (0..graph.n)
+        .map(|v| {
+            if reached[v] > 0 {
+                reached[v] as f64 / totals[v] as f64
+            } else if timed_out {
+                0.0
+            } else {
+                closeness_centrality(graph, v)
+            }
+        })
+        .collect();
+    Closeness { values, sources: processed, exact: processed == graph.n, timed_out }
+}
+
+// Writes one "a,b" row per sampled pair to `path`, in sampling order, with a header line.
+fn write_pairs_csv<T: std::fmt::Display>(path: &str, pairs: &[(T, T)]) -> std::io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "a,b")?;
+    for (a, b) in pairs {
+        writeln!(out, "{},{}", a, b)?;
+    }
+    out.flush()
+}
+
+// Writes the sampled pairs and their distances to a Parquet file with columns
+// a, b and distance (u64). Unreachable pairs have a null distance.
+#[cfg(feature = "parquet")]
+fn write_pairs_parquet(path: &str, estimate: &DistanceEstimate) -> Result<(), Box<dyn std::error::Error>> {
+    use std::sync::Arc;
+    use arrow::array::{ArrayRef, UInt64Array};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use parquet::arrow::ArrowWriter;
+
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("a", DataType::UInt64, false),
+        Field::new("b", DataType::UInt64, false),
+        Field::new("distance", DataType::UInt64, true),
+    ]));
+    let a: ArrayRef = Arc::new(UInt64Array::from_iter_values(estimate.pairs.iter().map(|p| p.0 as u64)));
+    let b: ArrayRef = Arc::new(UInt64Array::from_iter_values(estimate.pairs.iter().map(|p| p.1 as u64)));
+    let distance: ArrayRef = Arc::new(estimate.distances.iter()
+        .map(|&d| finite_distance(d).map(|d| d as u64))
+        .collect::<UInt64Array>());
+    let batch = RecordBatch::try_new(schema.clone(), vec![a, b, distance])?;
+
+    let mut writer = ArrowWriter::try_new(File::create(path)?, schema, None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+// Writes one "vertex,closeness" row per vertex to `path`, with a header line.
+// Vertices are written by their original IDs (or names) from `ids`.
+fn write_closeness_csv(path: &str, closeness: &[f64], ids: &VertexMap) -> std::io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "vertex,closeness")?;
+    for (v, c) in closeness.iter().enumerate() {
+        writeln!(out, "{},{:.6}", ids.label(v), c)?;
+    }
+    out.flush()
+}
+
+// Tests for the analyses built on the library Graph (its own tests are in lib.rs)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Closeness CSV has one row per vertex matching the per-vertex computation
+    #[test]
+    fn test_closeness_csv_matches_per_vertex() {
+        let edges = vec![(0,1),(1,2),(0,3),(1,4)];
+        let graph = Graph::with_vertices(&edges, 6);
+        let mut rng = rand::thread_rng();
+        let result = compute_closeness(&graph, &Deadline::default(), &mut rng);
+        assert!(result.exact && !result.timed_out);
+        let closeness = result.values;
+
+        let path = std::env::temp_dir().join("ds210_closeness_test.csv");
+        let path = path.to_str().unwrap();
+        write_closeness_csv(path, &closeness, &VertexMap::identity(graph.n)).unwrap();
+        let contents = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let mut lines = contents.lines();
+        assert_eq!(lines.next(), Some("vertex,closeness"));
+        let rows: Vec<&str> = lines.collect();
+        assert_eq!(rows.len(), graph.n);
+        for (v, row) in rows.iter().enumerate() {
+            let parts: Vec<&str> = row.split(',').collect();
+            assert_eq!(parts[0].parse::<usize>().unwrap(), v);
+            let value: f64 = parts[1].parse().unwrap();
+            assert!((value - closeness_centrality(&graph, v)).abs() < 1e-6);
+        }
+
+        // Vertex 1 reaches 0,2,4 at distance 1 and 3 at distance 2: 4 / 5
+        assert!((closeness_centrality(&graph, 1) - 0.8).abs() < 1e-12);
+        // Vertex 5 is isolated
+        assert_eq!(closeness_centrality(&graph, 5), 0.0);
+    }
+
+    // Removing the center of a star disconnects every leaf from every other
+    #[test]
+    fn test_remove_hub_disconnects_star() {
+        let edges = vec![(0,1),(0,2),(0,3),(0,4),(0,5)];
+        let graph = Graph::with_vertices(&edges, 6);
+        let mut rng = rand::thread_rng();
+
+        let before = estimate_average_distance(&graph, &[1,2,3,4,5], 10, &mut rng);
+        assert_eq!(before.pairs.len(), 10);
+        assert_eq!(before.average(), Some(2.0));
+
+        let hubs = top_degree_vertices(&graph, 1);
+        assert_eq!(hubs, vec![0]);
+        let mut keep = vec![true; graph.n];
+        keep[0] = false;
+        let reduced = subgraph(&graph, &keep);
+        assert!(reduced.adjacency.iter().all(|a| a.is_empty()));
+
+        let after = estimate_average_distance(&reduced, &[1,2,3,4,5], 10, &mut rng);
+        assert_eq!(after.unreachable_pairs(), after.pairs.len());
+        assert_eq!(after.average(), None);
+    }
+
+    // Sampling every vertex gives the exact radius
+    #[test]
+    fn test_radius_sample_all_vertices_is_exact() {
+        // Path 0-1-2-3-4 with a branch 2-5: center 2 has eccentricity 2
+        let edges = vec![(0,1),(1,2),(2,3),(3,4),(2,5)];
+        let graph = Graph::with_vertices(&edges, 6);
+        let vertices: Vec<usize> = (0..6).collect();
+        let exact = graph.eccentricities(&vertices).into_iter().min().unwrap();
+        assert_eq!(exact, 2);
+
+        let mut rng = rand::thread_rng();
+        let est = estimate_radius(&graph, &vertices, 6, &Deadline::default(), &mut rng).unwrap();
+        assert_eq!((est.radius, est.vertex, est.sampled), (2, 2, 6));
+
+        // A partial sample can only over-estimate
+        let est = estimate_radius(&graph, &vertices, 2, &Deadline::default(), &mut rng).unwrap();
+        assert_eq!(est.sampled, 2);
+        assert!(est.radius >= exact);
+    }
+
+    // An already-expired deadline stops after the first vertex and is labeled partial
+    #[test]
+    fn test_algo_timeout_gives_partial_results() {
+        let edges = vec![(0,1),(1,2),(2,3),(3,4),(2,5)];
+        let graph = Graph::with_vertices(&edges, 6);
+        let vertices: Vec<usize> = (0..6).collect();
+        let deadline = Deadline::after_seconds(0.0);
+        assert!(deadline.expired());
+        let mut rng = rand::thread_rng();
+
+        let closeness = compute_closeness(&graph, &deadline, &mut rng);
+        assert_eq!(closeness.sources, 1);
+        assert!(closeness.timed_out && !closeness.exact);
+        assert_eq!(closeness.values.len(), 6);
+        // Every vertex except the lone source got a value from its BFS
+        assert_eq!(closeness.values.iter().filter(|&&c| c > 0.0).count(), 5);
+
+        let est = estimate_radius(&graph, &vertices, 6, &deadline, &mut rng).unwrap();
+        assert_eq!(eccentricities_until(&graph, &vertices, &deadline).len(), 1);
+        assert_eq!(eccentricities_until(&graph, &vertices, &Deadline::default()), vec![4, 3, 2, 3, 4, 3]);
+        assert_eq!(est.sampled, 1);
+        assert!(est.timed_out);
+        assert_eq!(est.radius, graph.eccentricities(&[est.vertex])[0]);
+    }
+
+    // Growth CSV counts add up to the number of vertices reachable from the start
+    #[test]
+    fn test_growth_csv_cumulative_counts() {
+        // 5 is in a separate component and must not be counted
+        let edges = vec![(0,1),(1,2),(0,3),(1,4),(5,6)];
+        let graph = Graph::with_vertices(&edges, 7);
+        let levels = bfs_levels(&graph, 0);
+        assert_eq!(levels, vec![vec![0], vec![1,3], vec![2,4]]);
+
+        let path = std::env::temp_dir().join("ds210_growth_test.csv");
+        let path = path.to_str().unwrap();
+        write_growth_csv(path, &levels).unwrap();
+        let contents = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let mut lines = contents.lines();
+        assert_eq!(lines.next(), Some("radius,count_at_radius,cumulative_count"));
+        let rows: Vec<Vec<usize>> = lines
+            .map(|l| l.split(',').map(|x| x.parse().unwrap()).collect())
+            .collect();
+        assert_eq!(rows, vec![vec![0,1,1], vec![1,2,3], vec![2,2,5]]);
+        let total: usize = rows.iter().map(|r| r[1]).sum();
+        assert_eq!(total, graph.bfs(0).len());
+        assert_eq!(rows.last().unwrap()[2], total);
+    }
+
+    // A self-loop on the first edge must not leave BFS stuck on one vertex
+    #[test]
+    fn test_start_falls_back_from_self_loop() {
+        let edges = vec![(3,3),(0,1),(1,2)];
+        let graph = Graph::with_vertices(&edges, 4);
+        let start = choose_start_vertex(&graph, edges[0].0).unwrap();
+        assert_eq!(start, 0);
+        assert_eq!(graph.bfs(start).len(), 3);
+
+        // A vertex with neighbors is kept as is
+        assert_eq!(choose_start_vertex(&graph, 2), Some(2));
+
+        // Only self-loops: nothing to start from
+        let loops = Graph::with_vertices(&[(0,0),(1,1)], 2);
+        assert_eq!(choose_start_vertex(&loops, 0), None);
+    }
+
+    // The logline has every key, in a fixed order, on a single line
+    #[test]
+    fn test_logline_keys_in_stable_order() {
+        let summary = RunSummary {
+            file: "edges.txt".to_string(),
+            vertices: 14113,
+            edges: 52126,
+            start: Some("0".to_string()),
+            visited: 14113,
+            pairs: 1000,
+            average: Some(5.25),
+            diameter: Some(15),
+            seed: Some(42),
+        };
+        let line = format_logline(&summary);
+        assert!(!line.contains('\n'));
+        let keys: Vec<&str> = line.split_whitespace().map(|f| f.split('=').next().unwrap()).collect();
+        assert_eq!(keys, vec!["file", "n", "m", "start", "visited", "pairs", "avg", "diameter", "seed"]);
+        assert_eq!(line, "file=edges.txt n=14113 m=52126 start=0 visited=14113 pairs=1000 avg=5.2500 diameter=15 seed=42");
+
+        let missing = RunSummary { start: None, average: None, diameter: None, seed: None, ..summary };
+        assert!(format_logline(&missing).ends_with("avg=NA diameter=NA seed=NA"));
+        assert!(format_logline(&missing).contains(" start=NA "));
+    }
+
+    // The most-probable path multiplies the largest probabilities, even over more hops
+    #[test]
+    fn test_most_probable_path() {
+        let edges = [(0,1,0.9),(1,2,0.9),(0,2,0.5),(2,3,1.0)];
+        let cost_edges: Vec<(usize, usize, f64)> = edges.iter()
+            .map(|&(u, v, p)| (u, v, probability_to_cost(p).unwrap()))
+            .collect();
+        let graph = WeightedGraph::with_vertices(&cost_edges, 5);
+
+        let cost = graph.dijkstra(0, 3).unwrap();
+        assert!(((-cost).exp() - 0.81).abs() < 1e-12);
+        assert_eq!(graph.dijkstra(0, 4), None);
+        assert_eq!(graph.dijkstra(2, 2), Some(0.0));
+
+        assert!(probability_to_cost(0.0).is_err());
+        assert!(probability_to_cost(1.5).is_err());
+        assert!(probability_to_cost(-0.2).is_err());
+        assert_eq!(probability_to_cost(1.0), Ok(0.0));
+    }
+
+    // The stats dump reports every metric with the right values
+    #[test]
+    fn test_dump_graph_stats() {
+        // Component {0,1,2,3,4}, edge 5-6, isolated 7, self-loop on 8
+        let edges = vec![(0,1),(1,2),(0,3),(1,4),(5,6),(8,8)];
+        let graph = Graph::with_vertices(&edges, 9);
+        let stats = compute_graph_stats(&graph);
+        assert_eq!(stats.edges, 5);
+        assert_eq!((stats.min_degree, stats.max_degree), (0, 3));
+        assert_eq!(stats.components, 4);
+        assert_eq!(stats.largest_component, 5);
+        assert_eq!(stats.isolated, 2);
+        assert_eq!(stats.leaves, 5);
+
+        let lines = format_graph_stats(&stats);
+        let expected = ["- Vertices: 9", "- Edges: 5", "- Density: 0.138889",
+                        "- Degree: min 0, max 3, mean 1.1111, median 1.0",
+                        "- Connected components: 4 (largest has 5 vertices)",
+                        "- Isolated vertices: 2", "- Leaves (degree 1): 5", "- Triangles: 0"];
+        assert_eq!(lines, expected);
+    }
+
+    // Matrix Market entries are converted from 1-based to 0-based vertices
+    #[test]
+    fn test_read_mtx_edge_list() {
+        let path = std::env::temp_dir().join("ds210_mtx_test.mtx");
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "%%MatrixMarket matrix coordinate pattern symmetric\n\
+                              % a comment\n\
+                              4 4 3\n\
+                              2 1\n\
+                              3 2\n\
+                              4 1\n").unwrap();
+        let edges = read_edges(path, EdgeFormat::Mtx, IdNormalization::Exact).unwrap();
+        assert_eq!(edges, vec![(1,0),(2,1),(3,0)]);
+        let graph = Graph::with_vertices(&edges, 4);
+        assert_eq!(graph.adjacency, vec![vec![1,3], vec![0,2], vec![1], vec![0]]);
+
+        // Real-valued variant keeps the values as weights
+        std::fs::write(path, "%%MatrixMarket matrix coordinate real general\n\
+                              3 3 2\n\
+                              1 2 0.5\n\
+                              2 3 0.25\n").unwrap();
+        let weighted = read_weighted_edges(path, EdgeFormat::Mtx, IdNormalization::Exact).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(weighted, vec![(0,1,0.5),(1,2,0.25)]);
+    }
+
+    // ALT bounds always bracket the exact distance
+    #[test]
+    fn test_alt_bounds_bracket_exact_distances() {
+        let edges = vec![(0,1),(1,2),(2,3),(3,4),(4,0),(2,5),(5,6),(6,7),(1,7)];
+        let graph = Graph::with_vertices(&edges, 8);
+        let landmarks = compute_landmarks(&graph, vec![0, 6]);
+
+        let mut pairs = Vec::new();
+        for a in 0..8 {
+            for b in (a + 1)..8 {
+                let (lower, upper) = landmarks.bounds(a, b).unwrap();
+                let exact = shortest_path(&graph, a, b);
+                assert!(lower <= exact && exact <= upper, "pair ({},{})", a, b);
+                pairs.push((a, b));
+            }
+        }
+
+        let alt = alt_estimate(&landmarks, &pairs).unwrap();
+        assert_eq!(alt.pairs, 28);
+        assert!(alt.mean_lower <= alt.mean_midpoint && alt.mean_midpoint <= alt.mean_upper);
+        assert!((alt.mean_gap - (alt.mean_upper - alt.mean_lower)).abs() < 1e-12);
+
+        // A landmark on the pair itself gives the exact distance
+        assert_eq!(landmarks.bounds(0, 6), Some((3, 3)));
+    }
+
+    // Pairs at the same depth from the start are rejected and counted
+    #[test]
+    fn test_exclude_same_level_pairs() {
+        // Levels from 0: {0}, {1,3}, {2,4}
+        let edges = vec![(0,1),(1,2),(0,3),(3,4)];
+        let graph = Graph::with_vertices(&edges, 5);
+        let levels = bfs_distances(&graph, 0);
+        let vertices: Vec<usize> = (0..5).collect();
+        let mut rng = rand::thread_rng();
+
+        let (pairs, excluded) = sample_pairs_where(&vertices, 100, &mut rng, |a, b| levels[a] != levels[b]);
+        assert!(pairs.iter().all(|&(a, b)| levels[a] != levels[b]));
+        assert_eq!(pairs.len(), 8);
+        assert_eq!(excluded, 2);
+    }
+
+    // Two triangles joined by one edge split cleanly into two communities
+    #[test]
+    fn test_modularity_two_communities() {
+        let edges = vec![(0,1),(1,2),(0,2),(3,4),(4,5),(3,5),(2,3)];
+        let graph = Graph::with_vertices(&edges, 6);
+
+        let split = vec![0,0,0,1,1,1];
+        let q = modularity(&graph, &split);
+        // Each side: 3 of 7 edges inside, degree 7 of 14
+        assert!((q - (2.0 * (3.0 / 7.0 - 0.25))).abs() < 1e-12);
+        assert!(q > 0.3);
+
+        let single = vec![0; 6];
+        assert!(modularity(&graph, &single).abs() < 1e-12);
+
+        // Communities read from a file, with a header and an unlisted vertex
+        let path = std::env::temp_dir().join("ds210_communities_test.csv");
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "vertex,community\n0,a\n1,a\n2,a\n3,b\n4,b\n").unwrap();
+        let community_of = read_communities(path, &VertexMap::identity(6)).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(community_of, vec![0,0,0,1,1,2]);
+    }
+
+    // Seeded runs dump the same distinct, in-range pairs
+    #[test]
+    fn test_dump_pairs_reproducible() {
+        let vertices: Vec<usize> = (0..50).collect();
+        let dir = std::env::temp_dir();
+        let mut dumps = Vec::new();
+        for run in 0..2 {
+            let mut rng = StdRng::seed_from_u64(42);
+            let pairs = sample_pairs(&vertices, 100, &mut rng);
+            let path = dir.join(format!("ds210_pairs_test_{}.csv", run));
+            let path = path.to_str().unwrap();
+            write_pairs_csv(path, &pairs).unwrap();
+            dumps.push(std::fs::read_to_string(path).unwrap());
+            std::fs::remove_file(path).unwrap();
+        }
+        assert_eq!(dumps[0], dumps[1]);
+
+        let rows: Vec<(usize, usize)> = dumps[0].lines().skip(1)
+            .map(|l| {
+                let (a, b) = l.split_once(',').unwrap();
+                (a.parse().unwrap(), b.parse().unwrap())
+            })
+            .collect();
+        assert_eq!(rows.len(), 100);
+        let unique: HashSet<(usize, usize)> = rows.iter().copied().collect();
+        assert_eq!(unique.len(), rows.len());
+        assert!(rows.iter().all(|&(a, b)| a < b && b < 50));
+    }
+
+    // The 2-core keeps the cycle and prunes every pendant chain
+    #[test]
+    fn test_two_core_prunes_pendants() {
+        // Cycle 0-1-2-3 with pendant 4 on 0 and chain 1-5-6
+        let edges = vec![(0,1),(1,2),(2,3),(3,0),(0,4),(1,5),(5,6)];
+        let graph = Graph::with_vertices(&edges, 7);
+        let keep = k_core(&graph, 2);
+        assert_eq!(keep, vec![true, true, true, true, false, false, false]);
+
+        let core = subgraph(&graph, &keep);
+        assert_eq!(core.num_edges(), 4);
+        assert_eq!(core.bfs(0).len(), 4);
+
+        // A tree has an empty 2-core
+        let tree = Graph::with_vertices(&[(0,1),(1,2),(1,3)], 4);
+        assert!(k_core(&tree, 2).iter().all(|&k| !k));
+    }
+
+    // The hash ignores edge order and direction but notices a changed edge
+    #[test]
+    fn test_graph_hash_order_independent() {
+        let edges = vec![(0,1),(1,2),(0,3),(1,4)];
+        let shuffled = vec![(4,1),(0,3),(2,1),(1,0)];
+        let changed = vec![(0,1),(1,2),(0,3),(2,4)];
+        let ids = VertexMap::identity(5);
+        let hash = graph_hash(&Graph::with_vertices(&edges, 5), &ids);
+        assert_eq!(hash, graph_hash(&Graph::with_vertices(&shuffled, 5), &ids));
+        assert_ne!(hash, graph_hash(&Graph::with_vertices(&changed, 5), &ids));
+
+        // Adding a self-loop also changes the hash
+        let with_loop = vec![(0,1),(1,2),(0,3),(1,4),(2,2)];
+        assert_ne!(hash, graph_hash(&Graph::with_vertices(&with_loop, 5), &ids));
+
+        // After remapping, graphs with the same shape but different file IDs differ
+        let remapped_hash = |edges: &[(usize, usize)]| {
+            let (ids, dense) = VertexMap::remap(edges);
+            graph_hash(&Graph::with_vertices(&dense, ids.len()), &ids)
+        };
+        let path = remapped_hash(&[(1,2),(2,3)]);
+        assert_ne!(path, remapped_hash(&[(1,2),(2,7)]));
+        assert_ne!(path, remapped_hash(&[(10,20),(20,30)]));
+        assert_eq!(path, remapped_hash(&[(3,2),(2,1)]));
+        assert_eq!(remapped_hash(&[(0,1),(1,2)]), graph_hash(&Graph::with_vertices(&[(0,1),(1,2)], 3), &VertexMap::identity(3)));
+    }
+
+    // Checks every vertex triple, for comparison with the fast count
+    fn triangle_count_naive(graph: &Graph) -> u64 {
+        let has_edge = |a: usize, b: usize| graph.contains_edge(a, b);
+        let mut count = 0;
+        for a in 0..graph.n {
+            for b in (a + 1)..graph.n {
+                for c in (b + 1)..graph.n {
+                    if has_edge(a, b) && has_edge(b, c) && has_edge(a, c) {
+                        count += 1;
+                    }
+                }
+            }
+        }
+        count
+    }
+
+    // K4 has 4 triangles, and the fast count agrees with the naive one
+    #[test]
+    fn test_triangle_count_fast() {
+        let k4 = Graph::with_vertices(&[(0,1),(0,2),(0,3),(1,2),(1,3),(2,3)], 4);
+        assert_eq!(triangle_count_fast(&k4), 4);
+        assert_eq!(triangle_count_naive(&k4), 4);
+
+        // Triangle fan around a hub plus a self-loop and a tail
+        let edges = vec![(0,1),(0,2),(0,3),(0,4),(0,5),(1,2),(2,3),(3,4),(4,5),(5,5),(5,6),(1,3)];
+        let graph = Graph::with_vertices(&edges, 7);
+        assert_eq!(triangle_count_fast(&graph), triangle_count_naive(&graph));
+        assert_eq!(triangle_count_fast(&graph), 6);
+    }
+
+    // p = 1 keeps every edge, and a fixed seed keeps the same subset
+    #[test]
+    fn test_edge_sample() {
+        let edges: Vec<(usize, usize)> = (0..200).map(|i| (i, i + 1)).collect();
+        let mut rng = rand::thread_rng();
+        assert_eq!(sample_edges(&edges, 1.0, &mut rng), edges);
+        assert!(sample_edges(&edges, 0.0, &mut rng).is_empty());
+
+        let first = sample_edges(&edges, 0.5, &mut StdRng::seed_from_u64(7));
+        let second = sample_edges(&edges, 0.5, &mut StdRng::seed_from_u64(7));
+        assert_eq!(first, second);
+        assert!(!first.is_empty() && first.len() < edges.len());
+        assert!(first.iter().all(|e| edges.contains(e)));
+    }
+
+    // The harmonic mean down-weights long distances compared to the arithmetic mean
+    #[test]
+    fn test_harmonic_mean() {
+        let estimate = DistanceEstimate {
+            pairs: vec![(0,1),(0,2),(0,3),(0,4)],
+            distances: vec![1, 2, 4, usize::MAX],
+        };
+        let harmonic = estimate.harmonic_mean().unwrap();
+        let arithmetic = estimate.average().unwrap();
+        assert!((harmonic - 3.0 / 1.75).abs() < 1e-12);
+        assert!((arithmetic - 7.0 / 3.0).abs() < 1e-12);
+        assert!(harmonic < arithmetic);
+
+        // Equal distances give equal means; no reachable pairs gives None
+        let flat = DistanceEstimate { pairs: vec![(0,1),(1,2)], distances: vec![3, 3] };
+        assert_eq!(flat.harmonic_mean(), flat.average());
+        let none = DistanceEstimate { pairs: vec![(0,1)], distances: vec![usize::MAX] };
+        assert_eq!(none.harmonic_mean(), None);
+    }
+
+    // The self-test subcommand passes on this build
+    #[test]
+    fn test_selftest_passes() {
+        let checks = run_selftest();
+        for (name, passed) in &checks {
+            assert!(passed, "self-test check failed: {}", name);
+        }
+        assert!(parse_args(&["selftest".to_string()]).unwrap().selftest);
+    }
+
+    // Two merged graphs keep disjoint IDs and show up as two components
+    #[test]
+    fn test_union_disjoint_components() {
+        let first = vec![(0,1),(1,2)];
+        let second = vec![(0,1),(1,2),(2,0)];
+        let (merged, ranges) = union_edge_lists(&[first, second]);
+        assert_eq!(ranges, vec![0..3, 3..6]);
+        assert_eq!(merged, vec![(0,1),(1,2),(3,4),(4,5),(5,3)]);
+
+        let graph = Graph::with_vertices(&merged, 6);
+        let mut components = graph.connected_components();
+        for c in &mut components {
+            c.sort();
+        }
+        assert_eq!(components, vec![vec![0,1,2], vec![3,4,5]]);
+
+        let args: Vec<String> = ["--union", "a.txt", "b.txt", "--hash"].iter().map(|a| a.to_string()).collect();
+        let config = parse_args(&args).unwrap();
+        assert_eq!(config.union, vec!["a.txt", "b.txt"]);
+        assert!(config.hash);
+    }
+
+    // The bootstrap interval contains the sample mean and shrinks with more data
+    #[test]
+    fn test_bootstrap_interval() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let small: Vec<usize> = (0..30).map(|i| 1 + i % 6).collect();
+        let large: Vec<usize> = (0..3000).map(|i| 1 + i % 6).collect();
+        let mean = 3.5;
+
+        let small_boot = bootstrap_mean(&small, 500, &mut rng).unwrap();
+        let large_boot = bootstrap_mean(&large, 500, &mut rng).unwrap();
+        assert!(small_boot.lower <= mean && mean <= small_boot.upper);
+        assert!(large_boot.lower <= mean && mean <= large_boot.upper);
+        assert!(large_boot.upper - large_boot.lower < small_boot.upper - small_boot.lower);
+        assert!(large_boot.standard_error < small_boot.standard_error);
+
+        // A constant sample has no spread
+        let flat = bootstrap_mean(&[4, 4, 4], 50, &mut rng).unwrap();
+        assert_eq!((flat.standard_error, flat.lower, flat.upper), (0.0, 4.0, 4.0));
+        assert!(bootstrap_mean(&[], 50, &mut rng).is_none());
+    }
+
+    // Only vertices with the requested attribute value can be chosen as start
+    #[test]
+    fn test_start_where_attribute() {
+        // 0 is the overall hub but a person; 3 is the best-connected company
+        let edges = vec![(0,1),(0,2),(0,3),(0,4),(3,4),(3,5),(1,2)];
+        let graph = Graph::with_vertices(&edges, 6);
+        let path = std::env::temp_dir().join("ds210_attributes_test.csv");
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "vertex,category\n0,person\n1,company\n2,company\n3,company\n4,person\n9,company\n").unwrap();
+        let attributes = read_attributes(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(attributes.get(3, "category"), Some("company"));
+        assert_eq!(attributes.get(5, "category"), None);
+        assert_eq!(choose_start_where(&graph, &attributes, "category", "company"), Some(3));
+        assert_eq!(choose_start_where(&graph, &attributes, "category", "person"), Some(0));
+        assert_eq!(choose_start_where(&graph, &attributes, "category", "city"), None);
+        assert_eq!(choose_start_where(&graph, &attributes, "country", "company"), None);
+    }
+
+    // The six-degrees fraction counts pairs at or under the threshold
+    #[test]
+    fn test_six_degrees_fraction() {
+        let estimate = DistanceEstimate {
+            pairs: vec![(0,1),(0,2),(0,3),(0,4),(0,5)],
+            distances: vec![1, 6, 7, 3, usize::MAX],
+        };
+        assert_eq!(estimate.fraction_within(6), Some(0.6));
+        assert_eq!(estimate.fraction_within(7), Some(0.8));
+        assert_eq!(estimate.fraction_within(0), Some(0.0));
+
+        let args: Vec<String> = ["--six-degrees-hops", "3"].iter().map(|a| a.to_string()).collect();
+        let config = parse_args(&args).unwrap();
+        assert!(config.six_degrees);
+        assert_eq!(config.six_degrees_hops, 3);
+        assert_eq!(parse_args(&["--six-degrees".to_string()]).unwrap().six_degrees_hops, 6);
+    }
+
+    // Pair distances written to Parquet read back unchanged, with nulls for unreachable
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_parquet_round_trip() {
+        use arrow::array::{Array, UInt64Array};
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let estimate = DistanceEstimate {
+            pairs: vec![(0,1),(2,5),(3,4)],
+            distances: vec![1, usize::MAX, 4],
+        };
+        let path = std::env::temp_dir().join("ds210_pairs_test.parquet");
+        let path = path.to_str().unwrap();
+        write_pairs_parquet(path, &estimate).unwrap();
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap()).unwrap()
+            .build().unwrap();
+        let batches: Vec<_> = reader.map(|b| b.unwrap()).collect();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(batches.len(), 1);
+        let column = |i: usize| batches[0].column(i).as_any().downcast_ref::<UInt64Array>().unwrap().clone();
+
+        let (a, b, distance) = (column(0), column(1), column(2));
+        assert_eq!(a.values().to_vec(), vec![0, 2, 3]);
+        assert_eq!(b.values().to_vec(), vec![1, 5, 4]);
+        assert_eq!(distance.iter().collect::<Vec<_>>(), vec![Some(1), None, Some(4)]);
+        assert_eq!(distance.null_count(), 1);
+    }
+
+    // Avoiding a cut vertex disconnects the two sides of the graph
+    #[test]
+    fn test_avoid_cut_vertex() {
+        // Triangle 0-1-2 joined to triangle 3-4-5 only through vertex 2-3
+        let edges = vec![(0,1),(1,2),(0,2),(2,3),(3,4),(4,5),(3,5)];
+        let graph = Graph::with_vertices(&edges, 6);
+        let mut avoid = vec![false; 6];
+        assert_eq!(shortest_path_avoiding(&graph, 0, 5, &avoid), 3);
+
+        avoid[3] = true;
+        assert_eq!(shortest_path(&graph, 0, 4), 3);
+        assert_eq!(shortest_path_avoiding(&graph, 0, 4, &avoid), usize::MAX);
+        assert_eq!(shortest_path_avoiding(&graph, 0, 2, &avoid), 1);
+        assert_eq!(shortest_path_avoiding(&graph, 3, 4, &avoid), usize::MAX);
+
+        // Avoiding a vertex with a detour only lengthens the path
+        let mut detour = vec![false; 6];
+        detour[1] = true;
+        assert_eq!(shortest_path_avoiding(&graph, 0, 2, &detour), 1);
+        assert_eq!(shortest_path_avoiding(&graph, 1, 2, &detour), usize::MAX);
+    }
+
+    // Entropy of the distance histogram, in bits
+    #[test]
+    fn test_distance_entropy() {
+        let entropy_of = |distances: Vec<usize>| {
+            let pairs = (0..distances.len()).map(|i| (i, i + 1)).collect();
+            DistanceEstimate { pairs, distances }.distance_entropy()
+        };
+        assert_eq!(entropy_of(vec![3, 3, 3]), Some(0.0));
+        assert!((entropy_of(vec![1, 1, 2, 2]).unwrap() - 1.0).abs() < 1e-12);
+        assert!((entropy_of(vec![1, 2, 3, 4, usize::MAX]).unwrap() - 2.0).abs() < 1e-12);
+        // 1/2, 1/4, 1/4: 0.5 + 0.5 + 0.5 bits
+        assert!((entropy_of(vec![1, 1, 2, 3]).unwrap() - 1.5).abs() < 1e-12);
+        assert_eq!(entropy_of(vec![usize::MAX]), None);
+    }
+
+    // A run stopped partway is resumed from its checkpoint up to the full count
+    #[test]
+    fn test_checkpoint_resume() {
+        let edges: Vec<(usize, usize)> = (0..29).map(|i| (i, i + 1)).collect();
+        let graph = Graph::with_vertices(&edges, 30);
+        let vertices: Vec<usize> = (0..30).collect();
+        let path = std::env::temp_dir().join("ds210_checkpoint_test.txt");
+        let path = path.to_str().unwrap();
+        let mut rng = rand::thread_rng();
+        let ids = VertexMap::identity(graph.n);
+        let checkpoints = Checkpoints { path, every: 10, ids: &ids };
+
+        // "Interrupted" after 25 of 60 pairs, with checkpoints every 10
+        let partial = estimate_with_checkpoints(&graph, &vertices, 25, &mut rng, &checkpoints, None).unwrap();
+        let saved = read_checkpoint(path, &ids).unwrap();
+        assert_eq!(saved.pairs, partial.pairs);
+        assert_eq!(saved.distances, partial.distances);
+
+        let full = estimate_with_checkpoints(&graph, &vertices, 60, &mut rng, &checkpoints, Some(saved)).unwrap();
+        assert_eq!(full.pairs.len(), 60);
+        assert_eq!(&full.pairs[..25], &partial.pairs[..]);
+        let unique: HashSet<(usize, usize)> = full.pairs.iter().copied().collect();
+        assert_eq!(unique.len(), 60);
+        for (&(a, b), &d) in full.pairs.iter().zip(&full.distances) {
+            assert_eq!(d, b - a);
+        }
+        assert_eq!(read_checkpoint(path, &ids).unwrap().pairs.len(), 60);
+
+        // Tampered totals are rejected, as are vertices outside the graph
+        assert!(read_checkpoint(path, &VertexMap::identity(10)).is_none());
+        let contents = std::fs::read_to_string(path).unwrap();
+        std::fs::write(path, contents.replacen("total_distance", "total_distance 1", 1)).unwrap();
+        assert!(read_checkpoint(path, &ids).is_none());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    // Direction can only lengthen paths, and here it clearly does
+    #[test]
+    fn test_both_directions_differ() {
+        // Directed 4-cycle 0->1->2->3->0 with an extra one-way spur 1->4
+        let edges = vec![(0,1),(1,2),(2,3),(3,0),(1,4)];
+        let pairs = vec![(0,3),(3,1),(0,2),(4,1),(1,0)];
+        let (directed, undirected) = directed_and_undirected_estimates(&edges, 5, &pairs);
+
+        assert_eq!(directed.distances, vec![3, 2, 2, usize::MAX, 3]);
+        assert_eq!(undirected.distances, vec![1, 2, 2, 1, 1]);
+        assert_eq!(directed.unreachable_pairs(), 1);
+        assert_eq!(directed.average(), Some(2.5));
+        assert_eq!(undirected.average(), Some(1.4));
+        assert!(directed.distances.iter().zip(&undirected.distances).all(|(d, u)| d >= u));
+    }
+
+    // Peripheral landmarks come from the highest-eccentricity candidates
+    #[test]
+    fn test_peripheral_landmarks() {
+        // Path 0..=8: eccentricity is largest at the two ends
+        let edges: Vec<(usize, usize)> = (0..8).map(|i| (i, i + 1)).collect();
+        let graph = Graph::with_vertices(&edges, 9);
+        let vertices: Vec<usize> = (0..9).collect();
+        let mut rng = rand::thread_rng();
+
+        let samples = sampled_eccentricities(&graph, &vertices, 9, &mut rng);
+        assert_eq!(samples.len(), 9);
+        assert!(samples.iter().all(|&(v, e)| e == v.max(8 - v)));
+
+        // 4 candidates per landmark covers the whole path
+        let mut chosen = select_peripheral_landmarks(&graph, &vertices, 3, &mut rng);
+        chosen.sort();
+        assert_eq!(chosen, vec![0, 1, 8]);
+
+        let args: Vec<String> = ["--landmark-strategy", "peripheral"].iter().map(|a| a.to_string()).collect();
+        assert_eq!(parse_args(&args).unwrap().landmark_strategy, LandmarkStrategy::Peripheral);
+    }
+
+    // Edge lookups in both directions, for missing edges and out-of-range vertices
+    #[test]
+    fn test_contains_edge() {
+        let edges = vec![(0,1),(1,2),(0,3),(1,4),(4,4)];
+        let graph = Graph::with_vertices(&edges, 6);
+        assert!(graph.adjacency.iter().all(|a| a.windows(2).all(|w| w[0] < w[1])));
+
+        assert!(graph.contains_edge(0, 1) && graph.contains_edge(1, 0));
+        assert!(graph.contains_edge(4, 1) && graph.contains_edge(4, 4));
+        assert!(!graph.contains_edge(0, 2));
+        assert!(!graph.contains_edge(5, 0));
+        assert!(!graph.contains_edge(0, 6) && !graph.contains_edge(100, 1));
+
+        let args: Vec<String> = ["--has-edge", "3", "0"].iter().map(|a| a.to_string()).collect();
+        assert_eq!(parse_args(&args).unwrap().has_edge, Some((3, 0)));
+        assert!(parse_args(&args[..2]).is_err());
+    }
+
+    // Mean distance from vertex 0 to everything it reaches
+    #[test]
+    fn test_single_source_average() {
+        // Same graph as the BFS tests, plus an unreachable edge 5-6
+        let edges = vec![(0,1),(1,2),(0,3),(1,4),(5,6)];
+        let graph = Graph::with_vertices(&edges, 7);
+        // Distances from 0: 1 -> 1, 2 -> 2, 3 -> 1, 4 -> 2
+        assert_eq!(single_source_average(&graph, 0), Some((1.5, 4)));
+        assert_eq!(single_source_average(&graph, 5), Some((1.0, 1)));
+        assert_eq!(single_source_average(&graph, 9), None);
+    }
+
+    // A small, high-variance sample triggers the instability warning
+    #[test]
+    fn test_instability_warning() {
+        let noisy = DistanceEstimate {
+            pairs: (0..6).map(|i| (i, i + 1)).collect(),
+            distances: vec![1, 1, 1, 20, 20, 1],
+        };
+        let warning = instability_warning(&noisy, 0.05).unwrap();
+        assert!(warning.starts_with("the average is unstable"));
+
+        let steady = DistanceEstimate {
+            pairs: (0..1000).map(|i| (i, i + 1)).collect(),
+            distances: (0..1000).map(|i| 4 + i % 2).collect(),
+        };
+        assert!(steady.standard_error().unwrap() < 0.02);
+        assert_eq!(instability_warning(&steady, 0.05), None);
+        // Any spread at all trips a zero threshold
+        assert!(instability_warning(&steady, 0.0).is_some());
+    }
+
+    // Kruskal picks the hand-computed minimum and spans each component separately
+    #[test]
+    fn test_minimum_spanning_forest() {
+        let edges = [(0,1,4.0),(0,2,1.0),(1,2,2.0),(1,3,5.0),(2,3,8.0),(3,4,3.0),(5,6,1.5),(4,4,0.5)];
+        let (forest, total) = minimum_spanning_forest(7, &edges);
+        assert_eq!(forest, vec![(0,2,1.0),(5,6,1.5),(1,2,2.0),(3,4,3.0),(1,3,5.0)]);
+        assert!((total - 12.5).abs() < 1e-12);
+
+        // Unweighted: n - (number of components) edges of weight 1
+        let unweighted: Vec<(usize, usize, f64)> = vec![(0,1),(1,2),(0,2),(3,4)].into_iter()
+            .map(|(u, v)| (u, v, 1.0))
+            .collect();
+        let (forest, total) = minimum_spanning_forest(5, &unweighted);
+        assert_eq!(forest.len(), 3);
+        assert_eq!(total, 3.0);
+    }
+
+    // Every retained pair respects the minimum distance
+    #[test]
+    fn test_min_pair_distance() {
+        // Path 0..=9: 24 of the 45 pairs are 1 to 3 hops apart
+        let edges: Vec<(usize, usize)> = (0..9).map(|i| (i, i + 1)).collect();
+        let graph = Graph::with_vertices(&edges, 10);
+        let vertices: Vec<usize> = (0..10).collect();
+        let mut rng = rand::thread_rng();
+
+        let (estimate, rejected) = sample_far_pairs(&graph, &vertices, 100, 4, &mut rng);
+        assert_eq!(estimate.pairs.len(), 21);
+        assert_eq!(rejected, 24);
+        for (&(a, b), &d) in estimate.pairs.iter().zip(&estimate.distances) {
+            assert_eq!(d, b - a);
+            assert!(d >= 4);
+        }
+    }
+
+    // Collects log records so tests can check what was reported
+    struct CaptureLogger;
+    static CAPTURED: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+    impl log::Log for CaptureLogger {
+        fn enabled(&self, _: &log::Metadata) -> bool { true }
+        fn log(&self, record: &log::Record) {
+            CAPTURED.lock().unwrap().push(format!("{} {}", record.level(), record.args()));
+        }
+        fn flush(&self) {}
+    }
+    fn install_capture_logger() {
+        static INIT: std::sync::Once = std::sync::Once::new();
+        INIT.call_once(|| {
+            log::set_logger(&CaptureLogger).unwrap();
+            log::set_max_level(log::LevelFilter::Trace);
+        });
+    }
+
+    // Malformed Matrix Market entries are skipped with a warning naming the entry
+    #[test]
+    fn test_malformed_mtx_entry_warning() {
+        install_capture_logger();
+        let path = std::env::temp_dir().join("ds210_malformed_entries.mtx");
+        std::fs::write(&path, "3 3 2\n1 2\nnot-an-entry-xyz\n2 3\n").unwrap();
+        let path_str = path.to_str().unwrap();
+
+        let edges = read_edges(path_str, EdgeFormat::Mtx, IdNormalization::Exact).unwrap();
+        assert_eq!(edges, vec![(0, 1), (1, 2)]);
+        let captured = CAPTURED.lock().unwrap();
+        let warning = captured.iter().find(|m| m.contains("not-an-entry-xyz")).unwrap();
+        assert!(warning.starts_with("WARN"));
+        assert!(warning.contains(path_str));
+        drop(captured);
+        std::fs::remove_file(path).ok();
+    }
+
+    // A double sweep finds the exact diameter of a tree from any starting vertex
+    #[test]
+    fn test_double_sweep_diameter() {
+        // Spider with legs of length 1, 2 and 3 around vertex 0: diameter 5 between 3 and 6
+        let graph = Graph::with_vertices(&[(0,1),(0,2),(2,3),(0,4),(4,5),(5,6)], 7);
+        let vertices: Vec<usize> = (0..7).collect();
+        let mut rng = StdRng::seed_from_u64(261);
+        for _ in 0..10 {
+            let sweep = double_sweep_diameter(&graph, &vertices, &mut rng).unwrap();
+            assert_eq!(sweep.diameter, 5);
+            assert_eq!(graph.shortest_path(sweep.from, sweep.to), Some(5));
+        }
+        assert_eq!(graph.diameter(&vertices), 5);
+        assert!(double_sweep_diameter(&graph, &[], &mut rng).is_none());
+    }
+
+    // A three-column CSV loads as a graph and its weights reach Dijkstra, where
+    // the heavy direct edge loses to the lighter two-hop route
+    #[test]
+    fn test_weighted_csv_end_to_end() {
+        let path = std::env::temp_dir().join("ds210_weighted_edges.csv");
+        std::fs::write(&path, "0,1,1.0\n1,2,1.0\n0,2,5.0\n2,3,3.5\n").unwrap();
+        let path_str = path.to_str().unwrap();
+
+        let edges = read_edges(path_str, EdgeFormat::Csv, IdNormalization::Exact).unwrap();
+        assert_eq!(edges, vec![(0, 1), (1, 2), (0, 2), (2, 3)]);
+        let (ids, dense) = VertexMap::remap(&edges);
+        let graph = Graph::with_vertices(&dense, ids.len());
+        assert_eq!(graph.shortest_path(0, 2), Some(1));
+
+        let pairs = [(0, 2), (0, 3)];
+        let (average, count) = weighted_average_distance(path_str, EdgeFormat::Csv, IdNormalization::Exact, &ids, &pairs).unwrap().unwrap();
+        assert_eq!(count, 2);
+        assert!((average - (2.0 + 5.5) / 2.0).abs() < 1e-12);
+
+        // A lone data row with a weight is an edge, not a header
+        std::fs::write(&path, "0 1 3.5\n").unwrap();
+        assert_eq!(read_edges(path_str, EdgeFormat::Csv, IdNormalization::Exact).unwrap(), vec![(0, 1)]);
+        std::fs::remove_file(path).ok();
+    }
+
+    // The CSR export of a sparse-ID graph comes with the ID of each dense row
+    #[test]
+    fn test_write_csr_ids() {
+        let (ids, dense) = VertexMap::remap(&[(10, 20), (20, 35)]);
+        let graph = Graph::with_vertices(&dense, ids.len());
+        let prefix = std::env::temp_dir().join("ds210_csr_test");
+        let prefix = prefix.to_str().unwrap();
+        write_csr(prefix, &graph, &ids).unwrap();
+
+        let read = |suffix: &str| {
+            let path = format!("{}_{}.txt", prefix, suffix);
+            let contents = std::fs::read_to_string(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            contents
+        };
+        assert_eq!(read("indptr"), "0\n1\n3\n4\n");
+        assert_eq!(read("indices"), "1\n0\n2\n1\n");
+        assert_eq!(read("ids"), "10\n20\n35\n");
+    }
+
+    // Asking for more pairs than exist returns each pair once, without overflow
+    #[test]
+    fn test_sample_size_capped_at_pair_count() {
+        let vertices = [3, 1, 4, 5, 9];
+        let mut rng = StdRng::seed_from_u64(252);
+        let mut pairs = sample_pairs(&vertices, usize::MAX, &mut rng);
+        assert_eq!(pairs.len(), 10);
+        pairs.sort();
+        pairs.dedup();
+        assert_eq!(pairs.len(), 10);
+        assert!(pairs.iter().all(|&(a, b)| a < b));
+        assert_eq!(sample_ordered_pairs(&vertices, 20, &mut rng).len(), 20);
+        assert_eq!(pair_count(usize::MAX, true), usize::MAX);
+        assert_eq!(pair_count(1, false), 0);
+    }
+
+    // A malformed edge line is an error naming the line number and content
+    #[test]
+    fn test_malformed_line_error() {
+        let path = std::env::temp_dir().join("ds210_malformed_edges.csv");
+        std::fs::write(&path, "node_1,node_2\n0,1\nnot-an-edge-xyz\n1,2\n").unwrap();
+        let path_str = path.to_str().unwrap();
+
+        let err = read_edge_list(path_str).unwrap_err();
+        assert!(err.starts_with(&format!("{}:3:", path_str)));
+        assert!(err.contains("not-an-edge-xyz"));
+
+        // A file with only a header is reported as empty rather than malformed
+        std::fs::write(&path, "node_1,node_2\n").unwrap();
+        assert_eq!(read_edge_list(path_str).unwrap_err(), format!("{}: no edges found", path_str));
+        std::fs::remove_file(path).ok();
+    }
+
+    // Commas, spaces and tabs all delimit; '#' comments are skipped and a first
+    // line that is a real edge is kept rather than treated as a header
+    #[test]
+    fn test_read_edge_list_delimiters() {
+        let path = std::env::temp_dir().join("ds210_delimited_edges.txt");
+        std::fs::write(&path, "# SNAP-style comment\n0 1\n1\t2\n\n2, 3\n  # indented comment\n3  4\n").unwrap();
+        let path_str = path.to_str().unwrap();
+        assert_eq!(read_edge_list(path_str).unwrap(), vec![(0, 1), (1, 2), (2, 3), (3, 4)]);
+
+        std::fs::write(&path, "source\ttarget\tweight\n0\t1\t0.5\n1 2\n").unwrap();
+        assert_eq!(read_weighted_edge_list(path_str).unwrap(), vec![(0, 1, 0.5), (1, 2, 1.0)]);
+        std::fs::remove_file(path).ok();
+    }
+
+    // The line graph of a path is a shorter path; a star becomes a clique
+    #[test]
+    fn test_line_graph() {
+        // Path 0-1-2-3 has edges (0,1),(1,2),(2,3) -> line graph 0-1-2
+        let path = Graph::with_vertices(&[(0, 1), (1, 2), (2, 3)], 4);
+        let lg = line_graph(&path);
+        assert_eq!(lg.n, 3);
+        assert_eq!(lg.edges_iter().collect::<Vec<_>>(), vec![(0, 1), (1, 2)]);
+        assert_eq!(shortest_path(&lg, 0, 2), 2);
+
+        // Star with three leaves: every pair of edges shares the center
+        let star = Graph::with_vertices(&[(0, 1), (0, 2), (0, 3)], 4);
+        let lg = line_graph(&star);
+        assert_eq!((lg.n, lg.num_edges()), (3, 3));
+    }
+
+    // Names differing only in case or surrounding whitespace merge under --normalize-ids
+    #[test]
+    fn test_normalize_ids() {
+        let path = std::env::temp_dir().join("ds210_named_edges.csv");
+        std::fs::write(&path, "source,target\nAlice,bob\nalice ,Bob\n alice,carol\n").unwrap();
+        let path_str = path.to_str().unwrap();
+
+        let exact = read_named_edge_list(path_str, IdNormalization::Exact).unwrap();
+        assert_eq!(exact.names.len(), 6);
+        assert_eq!(exact.merged, 0);
+
+        let trimmed = read_named_edge_list(path_str, IdNormalization::Trim).unwrap();
+        assert_eq!(trimmed.names, vec!["Alice", "Bob", "alice", "bob", "carol"]);
+        assert_eq!(trimmed.merged, 1);
+
+        let lowered = read_named_edge_list(path_str, IdNormalization::Lowercase).unwrap();
+        assert_eq!(lowered.names, vec!["alice", "bob", "carol"]);
+        assert_eq!(lowered.edges, vec![(0, 1), (0, 1), (0, 2)]);
+        assert_eq!(lowered.merged, 3);
+
+        // --union reads each file with the requested normalization too
+        assert_eq!(read_edges(path_str, EdgeFormat::Names, IdNormalization::Lowercase).unwrap(), lowered.edges);
+        let weighted = read_weighted_edges(path_str, EdgeFormat::Names, IdNormalization::Lowercase).unwrap();
+        assert_eq!(weighted, vec![(0, 1, 1.0), (0, 1, 1.0), (0, 2, 1.0)]);
+        std::fs::remove_file(path).ok();
+    }
+
+    // Names are numbered in sorted order, so reordering the lines keeps the hash
+    #[test]
+    fn test_named_edges_order_independent() {
+        let dir = std::env::temp_dir();
+        let (first, second) = (dir.join("ds210_names_a.csv"), dir.join("ds210_names_b.csv"));
+        std::fs::write(&first, "source,target\ncarol,bob\nbob,alice\n").unwrap();
+        std::fs::write(&second, "source,target\nalice,bob\nbob,carol\n").unwrap();
+
+        let hash = |path: &std::path::Path| {
+            let named = read_named_edge_list(path.to_str().unwrap(), IdNormalization::Exact).unwrap();
+            assert_eq!(named.names, vec!["alice", "bob", "carol"]);
+            let (ids, dense) = VertexMap::remap(&named.edges);
+            graph_hash(&Graph::with_vertices(&dense, ids.len()), &ids)
+        };
+        assert_eq!(hash(&first), hash(&second));
+        std::fs::remove_file(first).ok();
+        std::fs::remove_file(second).ok();
+    }
+
+    // The bridge between two triangles is the most critical edge
+    #[test]
+    fn test_edge_criticality_bridge_first() {
+        // Triangles 0-1-2 and 3-4-5 joined by the bridge 2-3
+        let edges = vec![(0,1),(1,2),(0,2),(3,4),(4,5),(3,5),(2,3)];
+        let graph = Graph::with_vertices(&edges, 6);
+        let pairs: Vec<(usize, usize)> = (0..6).flat_map(|a| (a + 1..6).map(move |b| (a, b))).collect();
+
+        let impacts = edge_criticality(&graph, &pairs);
+        assert_eq!(impacts.len(), 7);
+        assert_eq!(impacts[0].edge, (2, 3));
+        assert_eq!(impacts[0].disconnected, 9);
+        // Every triangle edge keeps the graph connected and lengthens one pair by a hop
+        for impact in &impacts[1..] {
+            assert_eq!(impact.disconnected, 0);
+            assert!(impact.increase > 0.0);
+        }
+    }
+
+    // Unreachable distances render as a word, an empty CSV field or a null, never as usize::MAX
+    #[test]
+    fn test_unreachable_rendering() {
+        assert_eq!(format_distance(3, DistanceFormat::Text), "3");
+        assert_eq!(format_distance(3, DistanceFormat::Csv), "3");
+        assert_eq!(format_distance(usize::MAX, DistanceFormat::Text), "unreachable");
+        assert_eq!(format_distance(usize::MAX, DistanceFormat::Csv), "");
+        assert_eq!(finite_distance(usize::MAX), None);
+
+        let estimate = DistanceEstimate { pairs: vec![(0, 1), (2, 3)], distances: vec![1, usize::MAX] };
+        let path = std::env::temp_dir().join("ds210_unreachable_checkpoint.txt");
+        let path = path.to_str().unwrap();
+        let ids = VertexMap::identity(4);
+        write_checkpoint(path, &estimate, &ids).unwrap();
+        let contents = std::fs::read_to_string(path).unwrap();
+        assert!(contents.ends_with("0,1,1\n2,3,\n"));
+        assert!(!contents.contains(&usize::MAX.to_string()));
+        assert_eq!(read_checkpoint(path, &ids).unwrap().distances, estimate.distances);
+
+        // Checkpoints from before the empty-field convention still load
+        std::fs::write(path, contents.replace("2,3,\n", "2,3,-\n")).unwrap();
+        assert_eq!(read_checkpoint(path, &ids).unwrap().distances, estimate.distances);
+
+        // Pairs are stored by original ID and mapped back to dense vertices
+        let (sparse, _) = VertexMap::remap(&[(10, 20), (30, 40)]);
+        write_checkpoint(path, &estimate, &sparse).unwrap();
+        assert!(std::fs::read_to_string(path).unwrap().ends_with("10,20,1\n30,40,\n"));
+        assert_eq!(read_checkpoint(path, &sparse).unwrap().pairs, estimate.pairs);
+        assert!(read_checkpoint(path, &ids).is_none());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    // --samples sets the pair count, and 0 gives the exact all-pairs average
+    #[test]
+    fn test_samples_option() {
+        let parse = |args: &[&str]| parse_args(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>());
+        assert_eq!(parse(&[]).unwrap().samples, PAIR_SAMPLE_SIZE);
+        assert_eq!(parse(&["--samples", "5000"]).unwrap().samples, 5000);
+        assert_eq!(parse(&["--samples", "0"]).unwrap().samples, 0);
+        assert!(parse(&["--samples", "-3"]).unwrap_err().contains("Invalid sample count '-3'"));
+        assert!(parse(&["--samples"]).is_err());
+        assert!(parse(&["--samples", "0", "--exclude-same-level"]).is_err());
+        assert_eq!(parse(&["--samples", "0", "--dump-pairs", "pairs.csv"]).unwrap_err(),
+                   "--samples 0 measures no sampled pairs and cannot be combined with --dump-pairs");
+        assert!(parse(&["--samples", "0", "--edge-criticality"]).is_err());
+        // Analyses that do not use the sampled pairs still run after the exact average
+        assert!(parse(&["--samples", "0", "--diameter", "--csr", "out"]).unwrap().diameter);
+
+        // Path 0-1-2 plus the separate edge 3-4: from vertex 0's component the
+        // ordered pairs have distances 1,2,1,1,2,1
+        let graph = Graph::with_vertices(&[(0,1),(1,2),(3,4)], 5);
+        let (average, pairs) = exact_average_distance(&graph.distance_counts_from(&graph.bfs(0))).unwrap();
+        assert_eq!(pairs, 6);
+        assert!((average - 8.0 / 6.0).abs() < 1e-12);
+        assert_eq!(exact_average_distance(&graph.distance_counts_from(&[3])), Some((1.0, 1)));
+        let isolated = Graph::with_vertices(&[(0,1)], 3);
+        assert_eq!(exact_average_distance(&isolated.distance_counts_from(&[2])), None);
+    }
+
+    // The same --seed picks the same pairs and therefore the same average
+    #[test]
+    fn test_seed_reproducible() {
+        let args: Vec<String> = ["--seed", "253"].iter().map(|a| a.to_string()).collect();
+        let seed = parse_args(&args).unwrap().seed;
+        assert_eq!(seed, Some(253));
+        assert!(parse_args(&["--seed".to_string(), "-1".to_string()]).is_err());
+
+        let edges: Vec<(usize, usize)> = (0..59).map(|i| (i, i + 1)).chain([(0, 30), (10, 50)]).collect();
+        let graph = Graph::with_vertices(&edges, 60);
+        let vertices: Vec<usize> = (0..60).collect();
+        let runs: Vec<DistanceEstimate> = (0..2)
+            .map(|_| estimate_average_distance(&graph, &vertices, 200, &mut seeded_rng(seed)))
+            .collect();
+        assert_eq!(runs[0].pairs, runs[1].pairs);
+        assert_eq!(runs[0].average(), runs[1].average());
+
+        let other = estimate_average_distance(&graph, &vertices, 200, &mut seeded_rng(Some(254)));
+        assert_ne!(other.pairs, runs[0].pairs);
+    }
+
+    // Directed sampling keeps pair order and rejects undirected-only options
+    #[test]
+    fn test_directed_ordered_pairs() {
+        let vertices: Vec<usize> = (0..4).collect();
+        let mut rng = StdRng::seed_from_u64(257);
+        // All 12 ordered pairs of 4 vertices, versus 6 unordered ones
+        let pairs = sample_ordered_pairs(&vertices, 100, &mut rng);
+        assert_eq!(pairs.len(), 12);
+        assert!(pairs.iter().all(|&(a, b)| a != b));
+        assert!(pairs.iter().any(|&(a, b)| a > b));
+        assert_eq!(sample_pairs(&vertices, 100, &mut rng).len(), 6);
+
+        // On the directed path 0->1->2->3 only the forward half is reachable
+        let graph = Graph::with_directed_edges(&[(0,1),(1,2),(2,3)], 4);
+        let estimate = distances_for_pairs(&graph, pairs);
+        assert_eq!((estimate.counted_pairs(), estimate.unreachable_pairs()), (6, 6));
+        for (&(a, b), &d) in estimate.pairs.iter().zip(&estimate.distances) {
+            assert_eq!(d, if a < b { b - a } else { usize::MAX });
+        }
+
+        let args: Vec<String> = ["--directed", "--two-core"].iter().map(|a| a.to_string()).collect();
+        assert_eq!(parse_args(&args).unwrap_err(), "--directed cannot be combined with --two-core");
+        assert!(parse_args(&args[..1]).unwrap().directed);
+        let args: Vec<String> = ["--directed", "--radius-sample", "5"].iter().map(|a| a.to_string()).collect();
+        assert_eq!(parse_args(&args).unwrap_err(), "--directed cannot be combined with --radius-sample");
+        assert_eq!(parse_args(&args[1..]).unwrap().radius_sample, Some(5));
+        let args: Vec<String> = ["--radius-sample", "0"].iter().map(|a| a.to_string()).collect();
+        assert_eq!(parse_args(&args).unwrap_err(), "Invalid sample size '0' for --radius-sample");
+        let args: Vec<String> = ["--weighted", "--samples", "0"].iter().map(|a| a.to_string()).collect();
+        assert!(parse_args(&args).unwrap_err().starts_with("--weighted cannot be combined"));
+        let args: Vec<String> = ["--edge-sample", "0.5", "--mst", "tree.csv"].iter().map(|a| a.to_string()).collect();
+        assert_eq!(parse_args(&args).unwrap_err(), "--edge-sample cannot be combined with --mst");
+        let args: Vec<String> = ["--edge-sample", "0.5", "--probability-weights"].iter().map(|a| a.to_string()).collect();
+        assert_eq!(parse_args(&args).unwrap_err(), "--edge-sample cannot be combined with --probability-weights");
+        assert_eq!(parse_args(&args[..2]).unwrap().edge_sample, Some(0.5));
+    }
+
+    // Options that read the original input file are refused on the line graph
+    #[test]
+    fn test_line_graph_conflicts() {
+        let parse = |args: &[&str]| parse_args(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>());
+        assert_eq!(parse(&["--line-graph", "--probability-weights"]).unwrap_err(),
+                   "--line-graph cannot be combined with --probability-weights");
+        assert_eq!(parse(&["--line-graph", "--mst", "forest.csv"]).unwrap_err(),
+                   "--line-graph cannot be combined with --mst");
+        assert_eq!(parse(&["--line-graph", "--avoid", "blocked.txt"]).unwrap_err(),
+                   "--line-graph cannot be combined with --avoid");
+        assert_eq!(parse(&["--line-graph", "--compare-search", "0", "3"]).unwrap_err(),
+                   "--line-graph cannot be combined with --compare-search");
+        assert_eq!(parse(&["--single-source-average", "2", "--line-graph"]).unwrap_err(),
+                   "--line-graph cannot be combined with --single-source-average");
+        assert!(parse(&["--line-graph"]).unwrap().line_graph);
+        assert!(parse(&["--both-directions", "--line-graph"]).is_err());
+        assert!(parse(&["--both-directions", "--two-core"]).is_err());
     }
 }